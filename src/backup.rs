@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::downloaderror::DownloadError;
+
+/// The directory in a server directory that backups are kept in.
+pub const BACKUP_DIRECTORY: &str = "backups";
+
+/// Copies everything in the current directory into `backups/<name>` and returns where it went.
+///
+/// Earlier backups and the top-level entries in `exclude`, such as the Java runtimes, are not copied.
+pub fn create_backup(name: &str, exclude: &[&str]) -> Result<String, DownloadError> {
+    let target = format!("{}/{}", BACKUP_DIRECTORY, name);
+    fs::create_dir_all(&target).map_err(|error| DownloadError::io(&target, error))?;
+
+    let excluded = exclude
+        .iter()
+        .chain([&BACKUP_DIRECTORY])
+        .filter_map(|path| fs::canonicalize(path).ok())
+        .collect::<Vec<PathBuf>>();
+
+    for entry in fs::read_dir(".").map_err(|error| DownloadError::io(".", error))? {
+        let entry = entry.map_err(|error| DownloadError::io(".", error))?;
+
+        if fs::canonicalize(entry.path()).is_ok_and(|path| excluded.contains(&path)) {
+            continue;
+        }
+
+        copy_entry(&entry.path(), &Path::new(&target).join(entry.file_name()))?;
+    }
+
+    Ok(target)
+}
+
+/// Copies the file or directory at `from` to `to`, including everything in it.
+pub(crate) fn copy_entry(from: &Path, to: &Path) -> Result<(), DownloadError> {
+    let error = |error| DownloadError::io(&from.to_string_lossy(), error);
+
+    if from.is_dir() {
+        fs::create_dir_all(to).map_err(error)?;
+
+        for entry in fs::read_dir(from).map_err(error)? {
+            let entry = entry.map_err(error)?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(from, to).map_err(error)?;
+    }
+
+    Ok(())
+}
+
+/// Removes the file or directory at `path` if there is one. A trailing `/` is ignored.
+pub(crate) fn remove_entry(path: &str) -> Result<(), DownloadError> {
+    let path = path.trim_end_matches('/');
+
+    if Path::new(path).is_dir() {
+        fs::remove_dir_all(path).map_err(|error| DownloadError::io(path, error))
+    } else if Path::new(path).exists() {
+        fs::remove_file(path).map_err(|error| DownloadError::io(path, error))
+    } else {
+        Ok(())
+    }
+}
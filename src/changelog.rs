@@ -0,0 +1,132 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use semver::Version;
+use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::instance::INSTANCE_FILE;
+
+/// What changed in one build, as published by the distribution's upstream.
+#[derive(Clone, Debug)]
+pub struct BuildChanges {
+    /// The build number, or the version for distributions without builds.
+    pub build: String,
+    /// The day the build was published, e.g. `2024-04-25`.
+    pub date: Option<String>,
+    /// The channel the build was published on, if not the default one.
+    pub channel: Option<String>,
+    /// Whether the upstream flags the build as experimental or a pre-release.
+    pub experimental: bool,
+    /// One line per change, usually a commit summary.
+    pub changes: Vec<String>,
+}
+
+impl Display for BuildChanges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.build)?;
+
+        if let Some(date) = &self.date {
+            write!(f, " ({})", date)?;
+        }
+
+        if self.experimental {
+            write!(f, " [experimental]")?;
+        } else if let Some(channel) = &self.channel {
+            write!(f, " [{} channel]", channel)?;
+        }
+
+        if self.changes.is_empty() {
+            write!(f, "\n  No changes listed.")?;
+        }
+
+        for change in &self.changes {
+            write!(f, "\n  - {}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps the items whose build, as returned by `build`, comes after `from` and not after `to`, oldest first.
+///
+/// Without `to`, everything newer than `from` is kept. Without `from`, only the newest kept build is, since there is
+/// nothing to compare against. Builds are compared as numbers, or as versions for distributions without builds.
+pub(crate) fn select_builds<T>(mut items: Vec<T>, build: impl Fn(&T) -> &str, from: Option<&str>, to: Option<&str>) -> Vec<T> {
+    items.retain(|item| {
+        let after_from = from.is_none_or(|from| compare_builds(build(item), from) == Some(Ordering::Greater));
+        let until_to = to.is_none_or(|to| matches!(compare_builds(build(item), to), Some(Ordering::Less | Ordering::Equal)));
+
+        after_from && until_to
+    });
+    items.sort_by(|a, b| compare_builds(build(a), build(b)).unwrap_or(Ordering::Equal));
+
+    if from.is_none() {
+        items.split_off(items.len().saturating_sub(1))
+    } else {
+        items
+    }
+}
+
+fn compare_builds(a: &str, b: &str) -> Option<Ordering> {
+    if let (Ok(a), Ok(b)) = (a.parse::<u64>(), b.parse::<u64>()) {
+        return Some(a.cmp(&b));
+    }
+
+    match (Version::parse(a), Version::parse(b)) {
+        (Ok(a), Ok(b)) => Some(a.cmp(&b)),
+        _ => (a == b).then_some(Ordering::Equal),
+    }
+}
+
+/// Reads the changes of every build listed at `url`, an endpoint of PaperMC's build API, which GeyserMC uses as well.
+pub(crate) async fn get_papermc_changelog(http: &Http, url: &str, from: Option<&str>, to: Option<&str>) -> Result<Vec<BuildChanges>, DownloadError> {
+    let json = http.get_json(url).await?;
+
+    let builds = json["builds"]
+        .as_array()
+        .ok_or_else(|| DownloadError::schema(url, "missing builds array"))?
+        .iter()
+        .map(|build| {
+            let number = build["build"].as_u64().ok_or_else(|| DownloadError::schema(url, "build without a number"))?;
+            let channel = build["channel"].as_str().filter(|channel| *channel != "default");
+
+            Ok(BuildChanges {
+                build: number.to_string(),
+                date: build["time"].as_str().and_then(|time| time.get(..10)).map(|date| date.to_string()),
+                channel: channel.map(|channel| channel.to_string()),
+                experimental: channel == Some("experimental"),
+                changes: build["changes"]
+                    .as_array()
+                    .map(|changes| changes.iter().filter_map(|change| first_line(change["summary"].as_str()?)).collect())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect::<Result<Vec<BuildChanges>, DownloadError>>()?;
+
+    Ok(select_builds(builds, |build| &build.build, from, to))
+}
+
+/// Returns the first line of a commit message, or `None` if it is empty.
+pub(crate) fn first_line(message: &str) -> Option<String> {
+    message.lines().map(|line| line.trim()).find(|line| !line.is_empty()).map(|line| line.to_string())
+}
+
+/// Turns a timestamp in seconds since the Unix epoch into a date like `2024-04-25`, in UTC.
+pub(crate) fn date_from_timestamp(timestamp: u64) -> String {
+    // Converts days since the epoch to a civil date, see https://howardhinnant.github.io/date_algorithms.html.
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Returns `value`, a field of the installed [`Instance`](crate::instance::Instance), or an error naming `what` if it was not recorded.
+pub(crate) fn installed<'a>(value: &'a Option<String>, what: &str) -> Result<&'a str, DownloadError> {
+    value.as_deref().ok_or_else(|| DownloadError::invalid_file(INSTANCE_FILE, &format!("the installed {} is unknown, reinstall the server first", what)))
+}
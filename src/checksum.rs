@@ -0,0 +1,106 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use sha2::{Sha256, Sha512};
+
+/// A digest an upstream publishes for a file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    /// The expected digest, in lowercase hex.
+    pub value: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Computes a digest from data fed to it in chunks, as [`file_checksum`] does to check a file once it is downloaded.
+pub enum Hasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Checksum {
+    pub fn new(algorithm: ChecksumAlgorithm, value: &str) -> Checksum {
+        Checksum { algorithm, value: value.trim().to_lowercase() }
+    }
+}
+
+impl ChecksumAlgorithm {
+    pub fn hasher(&self) -> Hasher {
+        match self {
+            ChecksumAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            ChecksumAlgorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            ChecksumAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the digest of everything passed to [`Hasher::update`].
+    pub fn finish(self) -> Checksum {
+        match self {
+            Hasher::Md5(hasher) => Checksum::new(ChecksumAlgorithm::Md5, &format!("{:x}", hasher.finalize())),
+            Hasher::Sha1(hasher) => Checksum::new(ChecksumAlgorithm::Sha1, &format!("{:x}", hasher.finalize())),
+            Hasher::Sha256(hasher) => Checksum::new(ChecksumAlgorithm::Sha256, &format!("{:x}", hasher.finalize())),
+            Hasher::Sha512(hasher) => Checksum::new(ChecksumAlgorithm::Sha512, &format!("{:x}", hasher.finalize())),
+        }
+    }
+}
+
+/// Computes the `algorithm` digest of the file at `path`.
+pub fn file_checksum(path: &str, algorithm: ChecksumAlgorithm) -> io::Result<Checksum> {
+    let mut file = File::open(path)?;
+    let mut hasher = algorithm.hasher();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+
+        if read == 0 {
+            return Ok(hasher.finish());
+        }
+
+        hasher.update(&buffer[..read]);
+    }
+}
+
+impl Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ChecksumAlgorithm::Md5 => "md5",
+            ChecksumAlgorithm::Sha1 => "sha1",
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.value)
+    }
+}
@@ -0,0 +1,608 @@
+use std::panic;
+use std::path::{absolute, Path};
+use clap::{Parser, Subcommand, ValueEnum};
+use minecraft_server_installer::config::ConfigFile;
+use minecraft_server_installer::downloader::{cache_artifact, Installer};
+use minecraft_server_installer::downloaderror::DownloadError;
+use minecraft_server_installer::downloaders::installers;
+use minecraft_server_installer::history::{restore_snapshot, snapshots};
+use minecraft_server_installer::http::{now, Http};
+use minecraft_server_installer::install::{execute_plan, get_installed_server, plan_install, plan_locked_install, resolve_version};
+use minecraft_server_installer::installplan::InstallPlan;
+use minecraft_server_installer::instance::{update_instance, Instance};
+use minecraft_server_installer::launch::{accept_eula, run_launch_file};
+use minecraft_server_installer::lockfile::Lockfile;
+use minecraft_server_installer::manifest::{Manifest, MANIFEST_FILE};
+use minecraft_server_installer::os::OS;
+use minecraft_server_installer::properties::set_server_port;
+use minecraft_server_installer::registry::Registry;
+use minecraft_server_installer::servertype::ServerType::Server;
+use minecraft_server_installer::upgrade::{execute_upgrade, plan_upgrade, UpgradeTarget};
+use minecraft_server_installer::versioninfo::VersionKind;
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_EULA_NOT_ACCEPTED: i32 = 3;
+pub const EXIT_NOT_INSTALLED: i32 = 4;
+pub const EXIT_VERSION_NOT_FOUND: i32 = 5;
+
+#[derive(Parser)]
+#[command(version, about = "Download and run any version of your favorite Minecraft server distribution.")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+    /// Never connect to the internet and only use what was cached before, e.g. with prefetch.
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Work on this instance instead of the current directory or the selected instance.
+    #[arg(long, global = true)]
+    pub instance: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Install a server into the current directory without prompting.
+    Install {
+        /// The distribution to install, e.g. paper, fabric or velocity.
+        #[arg(long = "type", required_unless_present = "locked", conflicts_with = "locked")]
+        server_type: Option<String>,
+        /// The Minecraft version to install. Defaults to the latest version.
+        #[arg(long = "mc", conflicts_with = "locked")]
+        minecraft_version: Option<String>,
+        /// Install exactly what msi-lock.toml in the current directory describes, failing if any download no longer matches it.
+        #[arg(long)]
+        locked: bool,
+        /// The amount of RAM to allocate to the server, in gigabytes.
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(1..))]
+        ram: i32,
+        /// The port the server should listen on.
+        #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+        port: Option<u16>,
+        /// Accept the Minecraft EULA (https://aka.ms/MinecraftEULA).
+        #[arg(long)]
+        accept_eula: bool,
+        /// Print what would be downloaded and created without installing anything.
+        #[arg(long)]
+        dry_run: bool,
+        /// How to print the plan when using --dry-run.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Run the server installed in the current directory.
+    Run,
+    /// Upgrade the installed server to its newest build or another Minecraft version, keeping worlds and settings.
+    Upgrade {
+        /// The Minecraft version to upgrade to, or latest. Defaults to the newest build of the installed version.
+        #[arg(long = "mc")]
+        minecraft_version: Option<String>,
+        /// Print what would change without upgrading anything.
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not back up the server directory into backups/ first.
+        #[arg(long)]
+        no_backup: bool,
+    },
+    /// Restore the install that the last upgrade replaced.
+    Rollback {
+        /// Also restore the backup taken before that upgrade, including worlds and configs.
+        #[arg(long)]
+        with_backup: bool,
+        /// List the installs that can be restored instead, newest first.
+        #[arg(long, conflicts_with = "with_backup")]
+        list: bool,
+    },
+    /// Show what changed in the builds after the installed one, marking experimental builds.
+    Changelog {
+        /// The build to show changes up to, or the version for Fabric and NeoForge. Defaults to the newest one of the installed version.
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Install, update and configure the server in the current directory to match a manifest.
+    Apply {
+        /// The manifest describing the server. Defaults to msi-server.toml in the server directory.
+        #[arg(long)]
+        file: Option<String>,
+    },
+    /// Download distributions into the cache so they can be installed with --offline later.
+    Prefetch {
+        /// The distributions to download, as TYPE or TYPE@VERSION, e.g. paper@1.20.4 or velocity.
+        #[arg(required = true)]
+        servers: Vec<String>,
+    },
+    /// Create, list, select, rename or delete named server instances.
+    Instance {
+        #[command(subcommand)]
+        command: InstanceCommand,
+    },
+    /// List the versions available for a distribution.
+    ListVersions {
+        /// The distribution to list versions for.
+        #[arg(long = "type")]
+        server_type: String,
+        /// Only list versions the upstream considers stable.
+        #[arg(long)]
+        stable: bool,
+        /// Only list versions of this kind.
+        #[arg(long, value_enum)]
+        kind: Option<KindFilter>,
+        /// Only list versions that run on this Java major version, e.g. 17.
+        #[arg(long)]
+        java: Option<i32>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum InstanceCommand {
+    /// Create an empty instance to install a server into with --instance.
+    Create {
+        name: String,
+    },
+    /// List all instances and what is installed in them.
+    List,
+    /// Use this instance whenever --instance is not given. Without a name, the current directory is used again.
+    Select {
+        name: Option<String>,
+    },
+    /// Rename an instance.
+    Rename {
+        name: String,
+        new_name: String,
+    },
+    /// Delete an instance, including its worlds and configs.
+    Delete {
+        name: String,
+        /// Confirm deleting everything in the instance.
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum KindFilter {
+    Release,
+    Snapshot,
+    PreRelease,
+    ReleaseCandidate,
+    Experimental,
+}
+
+impl From<KindFilter> for VersionKind {
+    fn from(kind: KindFilter) -> Self {
+        match kind {
+            KindFilter::Release => VersionKind::Release,
+            KindFilter::Snapshot => VersionKind::Snapshot,
+            KindFilter::PreRelease => VersionKind::PreRelease,
+            KindFilter::ReleaseCandidate => VersionKind::ReleaseCandidate,
+            KindFilter::Experimental => VersionKind::Experimental,
+        }
+    }
+}
+
+pub async fn execute(command: Commands, offline: bool, instance: Option<String>) -> i32 {
+    prepare_hook();
+
+    let downloaders = installers();
+    let os = OS::current();
+    let config = ConfigFile::load(&os);
+    let http = match config.http(offline) {
+        Ok(http) => http,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            return exit_code(&error);
+        }
+    };
+    let registry = Registry::load(&os);
+
+    let command = match command {
+        Commands::Instance { command } => return manage_instances(command, &registry),
+        // Paths given on the command line are relative to where we were started, not to the instance.
+        Commands::Apply { file } => Commands::Apply { file: file.map(|file| absolute(&file).map(|path| path.to_string_lossy().to_string()).unwrap_or(file)) },
+        command => command,
+    };
+
+    if let Err(error) = registry.enter(instance) {
+        eprintln!("Error: {}", error);
+        return exit_code(&error);
+    }
+
+    match command {
+        Commands::Install { server_type, minecraft_version, locked, ram, port, accept_eula: eula, dry_run, format } => {
+            let lockfile = match locked.then(Lockfile::load).transpose() {
+                Ok(lockfile) => lockfile,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    return exit_code(&error);
+                }
+            };
+
+            let server_type = lockfile.as_ref().map(|lockfile| lockfile.distribution.clone()).or(server_type).expect("--type is required without --locked");
+
+            let Some(server_object) = find_installer(&downloaders, &server_type) else {
+                return EXIT_USAGE;
+            };
+
+            if !eula && !dry_run {
+                eprintln!("You must accept the Minecraft EULA (https://aka.ms/MinecraftEULA) with --accept-eula.");
+                return EXIT_EULA_NOT_ACCEPTED;
+            }
+
+            if port.is_some() && server_object.get_type() != Server {
+                eprintln!("--port is only supported for servers. Start {} once and edit its config file instead.", server_object.get_name());
+                return EXIT_USAGE;
+            }
+
+            let plan = match &lockfile {
+                Some(lockfile) => plan_locked_install(server_object, lockfile, ram, &config, &os),
+                None => plan_latest_install(server_object, minecraft_version, ram, &config, &os, &http).await,
+            };
+
+            let plan = match plan {
+                Ok(plan) => plan,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    return exit_code(&error);
+                }
+            };
+
+            if dry_run {
+                match format {
+                    OutputFormat::Text => println!("{}", plan),
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&plan).expect("Failed to serialize install plan")),
+                }
+
+                return EXIT_SUCCESS;
+            }
+
+            if let Err(error) = execute_plan(server_object, &plan, &os, &http).await {
+                eprintln!("Error: {}", error);
+                return exit_code(&error);
+            }
+
+            accept_eula().await;
+
+            if let Some(port) = port {
+                set_server_port(port);
+
+                if let Err(error) = update_instance(|instance| instance.port = Some(port)) {
+                    eprintln!("Error: {}", error);
+                    return exit_code(&error);
+                }
+            }
+
+            println!("Your server is ready to go!");
+            EXIT_SUCCESS
+        }
+        Commands::Run => {
+            let Some(server_object) = get_installed_server(&downloaders) else {
+                eprintln!("No server is installed in this directory.");
+                return EXIT_NOT_INSTALLED;
+            };
+
+            let launch_file = if os == OS::Windows { "./launch.bat" } else { "./launch.sh" };
+            if !Path::new(launch_file).exists() {
+                eprintln!("{} was not found. Reinstall the server to create it.", launch_file);
+                return EXIT_NOT_INSTALLED;
+            }
+
+            run_launch_file(&os, server_object).await.unwrap_or(EXIT_FAILURE)
+        }
+        Commands::Upgrade { minecraft_version, dry_run, no_backup } => {
+            let Some((instance, server_object)) = Instance::load().and_then(|instance| {
+                let server_object = instance.installer(&downloaders)?;
+                Some((instance, server_object))
+            }) else {
+                eprintln!("No server is installed in this directory.");
+                return EXIT_NOT_INSTALLED;
+            };
+
+            let target = match minecraft_version.as_deref() {
+                None => UpgradeTarget::SameVersion,
+                Some("latest") => UpgradeTarget::Latest,
+                Some(version) => UpgradeTarget::Version(version.to_string()),
+            };
+
+            let plan = match plan_upgrade(server_object, &instance, target, &config, &os, &http).await {
+                Ok(plan) => plan,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    return exit_code(&error);
+                }
+            };
+
+            if plan.is_current() {
+                println!("{} is already up to date.", plan.install.distribution);
+                return EXIT_SUCCESS;
+            }
+
+            println!("Upgrading {}", plan);
+
+            if dry_run {
+                return EXIT_SUCCESS;
+            }
+
+            match execute_upgrade(server_object, &plan, !no_backup, &config, &os, &http).await {
+                Ok(backup) => {
+                    if let Some(backup) = backup {
+                        println!("The previous server was backed up to {}.", backup);
+                    }
+
+                    println!("Your server was upgraded!");
+                    EXIT_SUCCESS
+                }
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    exit_code(&error)
+                }
+            }
+        }
+        Commands::Rollback { with_backup, list } => {
+            let snapshots = snapshots();
+
+            if list {
+                if snapshots.is_empty() {
+                    println!("No previous installs are saved.");
+                }
+
+                for snapshot in &snapshots {
+                    let backup = if snapshot.backup.is_some() { ", with backup" } else { "" };
+                    println!("{} (replaced {}{})", snapshot.description(), ago(snapshot.created_at), backup);
+                }
+
+                return EXIT_SUCCESS;
+            }
+
+            let Some(snapshot) = snapshots.first() else {
+                eprintln!("There is no previous install to roll back to.");
+                return EXIT_FAILURE;
+            };
+
+            if with_backup && !snapshot.backup.as_ref().is_some_and(|backup| Path::new(backup).exists()) {
+                eprintln!("No backup was kept from before {} was replaced.", snapshot.description());
+                return EXIT_FAILURE;
+            }
+
+            if let Err(error) = restore_snapshot(snapshot, with_backup) {
+                eprintln!("Error: {}", error);
+                return exit_code(&error);
+            }
+
+            println!("Rolled back to {}.", snapshot.description());
+            EXIT_SUCCESS
+        }
+        Commands::Changelog { to } => {
+            let Some((instance, server_object)) = Instance::load().and_then(|instance| {
+                let server_object = instance.installer(&downloaders)?;
+                Some((instance, server_object))
+            }) else {
+                eprintln!("No server is installed in this directory.");
+                return EXIT_NOT_INSTALLED;
+            };
+
+            match server_object.changelog(http.clone(), &instance, to).await {
+                Ok(None) => {
+                    eprintln!("{} does not publish a changelog.", server_object.get_name());
+                    EXIT_FAILURE
+                }
+                Ok(Some(builds)) if builds.is_empty() => {
+                    println!("There are no newer builds of {}.", server_object.get_name());
+                    EXIT_SUCCESS
+                }
+                Ok(Some(builds)) => {
+                    let builds = builds.iter().map(|build| build.to_string()).collect::<Vec<String>>();
+                    println!("{}", builds.join("\n\n"));
+                    EXIT_SUCCESS
+                }
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    exit_code(&error)
+                }
+            }
+        }
+        Commands::Apply { file } => {
+            let file = file.unwrap_or(MANIFEST_FILE.to_string());
+            let manifest = match Manifest::load(&file) {
+                Ok(manifest) => manifest,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    return exit_code(&error);
+                }
+            };
+
+            let Some(server_object) = find_installer(&downloaders, &manifest.distribution) else {
+                return EXIT_USAGE;
+            };
+
+            if !manifest.accept_eula {
+                eprintln!("You must accept the Minecraft EULA (https://aka.ms/MinecraftEULA) with accept_eula = true in {}.", file);
+                return EXIT_EULA_NOT_ACCEPTED;
+            }
+
+            match manifest.apply(server_object, &config, &os, &http).await {
+                Ok(changes) if changes.is_empty() => {
+                    println!("Nothing to change, the server already matches {}.", file);
+                    EXIT_SUCCESS
+                }
+                Ok(changes) => {
+                    for change in changes {
+                        println!("{}.", change);
+                    }
+
+                    EXIT_SUCCESS
+                }
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    exit_code(&error)
+                }
+            }
+        }
+        Commands::Prefetch { servers } => {
+            let mut requested = vec![];
+
+            for server in &servers {
+                let (server_type, minecraft_version) = match server.split_once('@') {
+                    Some((server_type, minecraft_version)) => (server_type, Some(minecraft_version.to_string())),
+                    None => (server.as_str(), None),
+                };
+
+                let Some(server_object) = find_installer(&downloaders, server_type) else {
+                    return EXIT_USAGE;
+                };
+
+                requested.push((server_object, minecraft_version));
+            }
+
+            let mut code = EXIT_SUCCESS;
+
+            for (server_object, minecraft_version) in requested {
+                if let Err(error) = prefetch(server_object, minecraft_version, &config, &os, &http).await {
+                    eprintln!("Error: {}", error);
+
+                    if code == EXIT_SUCCESS {
+                        code = exit_code(&error);
+                    }
+                }
+            }
+
+            code
+        }
+        Commands::Instance { .. } => unreachable!("instance commands do not enter an instance"),
+        Commands::ListVersions { server_type, stable, kind, java } => {
+            let Some(server_object) = find_installer(&downloaders, &server_type) else {
+                return EXIT_USAGE;
+            };
+
+            match server_object.get_versions(http).await {
+                Ok(mut versions) => {
+                    config.set_java_versions(&mut versions);
+
+                    let versions = versions
+                        .iter()
+                        .filter(|version| !stable || version.stable)
+                        .filter(|version| kind.is_none_or(|kind| version.kind == kind.into()))
+                        .filter(|version| java.is_none_or(|java| version.java_major == Some(java)));
+
+                    for version in versions {
+                        println!("{}", version);
+                    }
+
+                    EXIT_SUCCESS
+                }
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    exit_code(&error)
+                }
+            }
+        }
+    }
+}
+
+fn manage_instances(command: InstanceCommand, registry: &Registry) -> i32 {
+    let result = match command {
+        InstanceCommand::Create { name } => registry.create(&name).map(|path| {
+            println!("Created instance {} in {}.", name, path);
+            println!("Install a server into it with: install --instance {} --type <TYPE> --accept-eula", name);
+        }),
+        InstanceCommand::List => {
+            let selected = registry.selected();
+
+            for name in registry.list() {
+                let marker = if selected.as_ref() == Some(&name) { "*" } else { " " };
+                let installed = Instance::read(&registry.instance_path(&name))
+                    .map(|instance| {
+                        let version = instance.minecraft_version.or(instance.version).unwrap_or_default();
+                        format!("{} {}", instance.distribution, version).trim().to_string()
+                    })
+                    .unwrap_or("empty".to_string());
+
+                println!("{} {} ({})", marker, name, installed);
+            }
+
+            Ok(())
+        }
+        InstanceCommand::Select { name } => registry.select(name.as_deref()).map(|_| match name {
+            Some(name) => println!("Selected instance {}.", name),
+            None => println!("Commands will use the current directory again."),
+        }),
+        InstanceCommand::Rename { name, new_name } => registry.rename(&name, &new_name).map(|_| println!("Renamed instance {} to {}.", name, new_name)),
+        InstanceCommand::Delete { name, yes: false } => {
+            eprintln!("Deleting {} removes its worlds and configs. Pass --yes to confirm.", name);
+            return EXIT_USAGE;
+        }
+        InstanceCommand::Delete { name, yes: true } => registry.delete(&name).map(|_| println!("Deleted instance {}.", name)),
+    };
+
+    match result {
+        Ok(()) => EXIT_SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// Resolves `minecraft_version`, where `latest` means the same as none, and plans installing it.
+async fn plan_latest_install(server_object: &dyn Installer, minecraft_version: Option<String>, ram: i32, config: &ConfigFile, os: &OS, http: &Http) -> Result<InstallPlan, DownloadError> {
+    let minecraft_version = resolve_version(server_object, minecraft_version.filter(|version| version != "latest"), http).await?;
+
+    plan_install(server_object, minecraft_version, ram, config, os, http).await
+}
+
+/// Caches everything needed to install `server_object` offline: its metadata, the server artifact and the Java runtime.
+async fn prefetch(server_object: &dyn Installer, minecraft_version: Option<String>, config: &ConfigFile, os: &OS, http: &Http) -> Result<(), DownloadError> {
+    let plan = plan_latest_install(server_object, minecraft_version, 3, config, os, http).await?;
+
+    cache_artifact(http, &plan.server.artifact.url, plan.server.artifact.checksum.as_ref()).await?;
+    cache_artifact(http, &plan.java.url, plan.java.checksum.as_ref()).await?;
+
+    println!("Cached {} {} with Java {}.", plan.distribution, plan.server.minecraft_version.or(plan.server.version).unwrap_or_default(), plan.java.major);
+
+    Ok(())
+}
+
+fn find_installer<'a>(downloaders: &'a [Box<dyn Installer>], server_type: &str) -> Option<&'a dyn Installer> {
+    let installer = minecraft_server_installer::downloaders::find_installer(downloaders, server_type);
+
+    if installer.is_none() {
+        let types = downloaders.iter().map(|downloader| downloader.get_id()).collect::<Vec<String>>();
+        eprintln!("Unknown server type '{}'. Available types: {}", server_type, types.join(", "));
+    }
+
+    installer
+}
+
+/// Describes how long ago `timestamp`, in seconds since the Unix epoch, was, e.g. `3 hours ago`.
+fn ago(timestamp: u64) -> String {
+    let seconds = now().saturating_sub(timestamp);
+
+    let (amount, unit) = match seconds {
+        0..60 => return "just now".to_string(),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+
+    format!("{} {}{} ago", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+fn exit_code(error: &DownloadError) -> i32 {
+    match error {
+        DownloadError::VersionNotFound { .. } => EXIT_VERSION_NOT_FOUND,
+        DownloadError::InstanceNotFound { .. } | DownloadError::InstanceExists { .. } | DownloadError::InvalidInstanceName { .. } => EXIT_USAGE,
+        _ => EXIT_FAILURE,
+    }
+}
+
+fn prepare_hook() {
+    panic::set_hook(Box::new(|panic_info| {
+        eprintln!("Error: {}", panic_info);
+
+        std::process::exit(EXIT_FAILURE);
+    }));
+}
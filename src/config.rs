@@ -1,10 +1,20 @@
-use std::fs;
+use std::{env, fs};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
+use std::path::{absolute, Path};
+use std::time::Duration;
+use reqwest::{Certificate, Client, NoProxy, Proxy};
 use serde::{Deserialize, Serialize};
 use toml::Value;
 use crate::downloader;
+use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::minecraftversion::MinecraftVersion;
+use crate::os::OS;
+use crate::versioninfo::VersionInfo;
 
+/// The `msi-config.toml` file holding Java download locations and version thresholds.
 #[derive(Clone)]
 pub struct ConfigFile {
     pub(crate) path: String
@@ -15,6 +25,13 @@ struct Config {
     java_paths: JavaPaths,
     java_downloads: JavaDownloads,
     java_version_thresholds: JavaVersionThresholds,
+    /// URL prefixes mapped to the base URLs to fetch them from instead, tried in order.
+    #[serde(default)]
+    upstreams: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    http: HttpSettings,
+    #[serde(default)]
+    history: HistorySettings,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -65,7 +82,215 @@ pub struct JavaVersionThresholds {
     java_21: String,
 }
 
+impl Default for JavaVersionThresholds {
+    fn default() -> Self {
+        JavaVersionThresholds {
+            java_16: "21w19a".to_string(),
+            java_17: "1.18-pre2".to_string(),
+            java_21: "24w14a".to_string(),
+        }
+    }
+}
+
+impl JavaVersionThresholds {
+    fn java_version_for(&self, minecraft_version: &MinecraftVersion) -> i32 {
+        if *minecraft_version >= MinecraftVersion::parse(&self.java_21) {
+            21
+        } else if *minecraft_version >= MinecraftVersion::parse(&self.java_17) {
+            17
+        } else if *minecraft_version >= MinecraftVersion::parse(&self.java_16) {
+            16
+        } else {
+            8
+        }
+    }
+}
+
+/// Returns the threshold named `key` from the `java_version_thresholds` table of `config`, if it is set.
+fn threshold(config: &Value, key: &str) -> Option<String> {
+    config.get("java_version_thresholds")?.get(key)?.as_str().map(|threshold| threshold.to_string())
+}
+
+/// Returns the Java major version needed to run `minecraft_version` according to the default thresholds.
+pub fn default_java_version(minecraft_version: &MinecraftVersion) -> i32 {
+    JavaVersionThresholds::default().java_version_for(minecraft_version)
+}
+
+/// Settings for the HTTP client every request goes through.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Sent with every request. Defaults to the installer's name, version and repository.
+    user_agent: Option<String>,
+    connect_timeout_seconds: u64,
+    /// How long a request may go without receiving data before it fails.
+    read_timeout_seconds: u64,
+    /// A proxy for all requests, e.g. `http://proxy.internal:3128`. Without one, `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` are used.
+    proxy: Option<String>,
+    /// A PEM file with extra certificates to trust, e.g. for a proxy that intercepts TLS.
+    ca_bundle: Option<String>,
+}
+
+/// How many previous installs are kept for rollbacks.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    /// The number of previous installs to keep. The backups taken before them are deleted along with them.
+    pub keep: usize,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        HistorySettings { keep: 3 }
+    }
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            user_agent: None,
+            connect_timeout_seconds: 10,
+            read_timeout_seconds: 30,
+            proxy: None,
+            ca_bundle: None,
+        }
+    }
+}
+
 impl ConfigFile {
+    /// Uses the config file in the directory at `path`.
+    pub fn new(path: String) -> ConfigFile {
+        ConfigFile { path }
+    }
+
+    /// Opens the config file in the default config directory for `os`, creating or repairing it if needed.
+    pub fn load(os: &OS) -> ConfigFile {
+        let path = if os == &OS::Windows {
+            env::var("APPDATA").unwrap_or("./".to_string()) + "\\MinecraftServerInstaller"
+        } else if os == &OS::Linux {
+            env::var("XDG_CONFIG_HOME").unwrap_or("./".to_string()) + "/MinecraftServerInstaller"
+        } else {
+            env::var("HOME").unwrap_or("./".to_string()) + "/Library/Application Support/MinecraftServerInstaller"
+        };
+
+        // Absolute, so the config is still found after entering an instance directory.
+        let config = ConfigFile::new(absolute(&path).map(|path| path.to_string_lossy().to_string()).unwrap_or(path));
+
+        if !Path::new(&(config.path.to_string() + "/msi-config.toml")).exists() {
+            if !Path::new(&config.path).exists() {
+                fs::create_dir_all(&config.path).expect("Failed to create config directory");
+            }
+
+            config.create();
+        }
+
+        config.test();
+
+        config
+    }
+
+    /// The directory holding the config file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The directory upstream metadata is cached in, see [`Http`].
+    pub fn cache_path(&self) -> String {
+        self.path.to_string() + "/cache"
+    }
+
+    /// The HTTP client for installers, using the `http` settings, cache and upstreams from this config.
+    ///
+    /// Fails if any of those settings are invalid.
+    pub fn http(&self, offline: bool) -> Result<Http, DownloadError> {
+        Ok(Http::new(self.client()?, Some(self.cache_path()), offline).with_upstreams(self.get_upstreams()?))
+    }
+
+    /// Builds a client from the `http` section.
+    pub fn client(&self) -> Result<Client, DownloadError> {
+        let settings = self.get_http_settings()?;
+        let user_agent = settings.user_agent.unwrap_or(format!("MinecraftServerInstaller/{} (+https://github.com/Loudbooks/Minecraft-Server-Installer)", env!("CARGO_PKG_VERSION")));
+
+        let mut builder = Client::builder()
+            .user_agent(user_agent)
+            .connect_timeout(Duration::from_secs(settings.connect_timeout_seconds))
+            .read_timeout(Duration::from_secs(settings.read_timeout_seconds));
+
+        if let Some(proxy) = settings.proxy.filter(|proxy| !proxy.is_empty()) {
+            let parsed = Proxy::all(&proxy).map_err(|error| self.invalid(&format!("proxy {} is invalid: {}", proxy, error)))?;
+            builder = builder.proxy(parsed.no_proxy(NoProxy::from_env()));
+        }
+
+        if let Some(ca_bundle) = settings.ca_bundle.filter(|ca_bundle| !ca_bundle.is_empty()) {
+            let pem = fs::read(&ca_bundle).map_err(|error| DownloadError::io(&ca_bundle, error))?;
+            let certificates = Certificate::from_pem_bundle(&pem).map_err(|error| self.invalid(&format!("ca_bundle {} is not a PEM file: {}", ca_bundle, error)))?;
+
+            if certificates.is_empty() {
+                return Err(self.invalid(&format!("ca_bundle {} contains no certificates", ca_bundle)));
+            }
+
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        builder.build().map_err(DownloadError::Network)
+    }
+
+    /// Returns the `http` section, with defaults for anything left out.
+    pub fn get_http_settings(&self) -> Result<HttpSettings, DownloadError> {
+        let config = self.get_config();
+
+        match config.get("http").cloned() {
+            Some(http) => http.try_into().map_err(|error| self.invalid(&format!("[http] is invalid: {}", error))),
+            None => Ok(HttpSettings::default()),
+        }
+    }
+
+    /// Returns the `history` section.
+    pub fn get_history_settings(&self) -> Result<HistorySettings, DownloadError> {
+        let config = self.get_config();
+
+        match config.get("history").cloned() {
+            Some(history) => history.try_into().map_err(|error| self.invalid(&format!("[history] is invalid: {}", error))),
+            None => Ok(HistorySettings::default()),
+        }
+    }
+
+    /// Returns the `upstreams` table: URL prefixes and the mirrors to use for them, in order.
+    pub fn get_upstreams(&self) -> Result<Vec<(String, Vec<String>)>, DownloadError> {
+        let config = self.get_config();
+
+        let Some(upstreams) = config.get("upstreams").and_then(|upstreams| upstreams.as_table()) else {
+            return Ok(vec![]);
+        };
+
+        upstreams
+            .iter()
+            .map(|(prefix, mirrors)| {
+                let invalid = || self.invalid(&format!("the mirrors for {} in [upstreams] must be a URL or a list of URLs", prefix));
+
+                let mirrors = match mirrors {
+                    Value::String(mirror) => vec![mirror.to_string()],
+                    mirrors => mirrors
+                        .as_array()
+                        .ok_or_else(invalid)?
+                        .iter()
+                        .map(|mirror| mirror.as_str().map(|mirror| mirror.to_string()).ok_or_else(invalid))
+                        .collect::<Result<Vec<String>, DownloadError>>()?,
+                };
+
+                Ok((prefix.to_string(), mirrors))
+            })
+            .collect()
+    }
+
+    /// An error pointing at a setting in the config file that cannot be used.
+    fn invalid(&self, message: &str) -> DownloadError {
+        DownloadError::invalid_file(&format!("{}/msi-config.toml", self.path), message)
+    }
+
+    /// Writes the default config file, overwriting any existing one.
     pub fn create(&self) {
         let path = self.path.clone().to_string();
         let file = File::create(format!("{path}/msi-config.toml")).expect("Failed to create config file");
@@ -76,6 +301,7 @@ impl ConfigFile {
         file.write_all(toml_config.as_bytes()).expect("Failed to write config to file");
     }
 
+    /// Regenerates the config file if it is missing or can no longer be parsed, keeping a backup of the old one.
     pub fn test(&self) {
         let string = fs::read_to_string(self.path.to_string() + "/msi-config.toml");
 
@@ -92,6 +318,7 @@ impl ConfigFile {
         }
     }
 
+    /// Returns the Java download URL for a `java_downloads` key prefix (see [`OS::java_key`]) and Java major version.
     pub fn get_java_download(&self, key: String, version: i32) -> Option<String> {
         let config = self.clone().get_config();
 
@@ -101,6 +328,7 @@ impl ConfigFile {
         Some(java_download)
     }
 
+    /// Returns the path of the Java executable relative to the Java install directory.
     pub fn get_java_path(&self, key: String, version: i32) -> Option<String> {
         let config = self.get_config();
 
@@ -109,6 +337,7 @@ impl ConfigFile {
         Some(java_paths.get(key + "_" + version.to_string().as_str()).expect("Failed to get java_path key").as_str().expect("Failed to get java_path as string").to_string())
     }
 
+    /// Returns the directory Java runtimes are extracted into.
     pub fn get_java_install_path(&self) -> Option<String> {
         let config = self.get_config();
 
@@ -117,34 +346,45 @@ impl ConfigFile {
         Some(java_install_paths.get("java_install_paths").expect("Failed to get java_install_path key").as_str().expect("Failed to get java_install_path as string").to_string())
     }
 
+    /// Returns the first Minecraft version that requires the Java version named by `key`, e.g. `java_17`.
     pub fn get_java_version_threshold(&self, key: String) -> Option<String> {
+        threshold(&self.get_config(), &key)
+    }
+
+    /// Returns the Java major version needed to run `minecraft_version`, or the latest release if `None`.
+    pub async fn get_java_version(&self, http: &Http, minecraft_version: Option<String>) -> Result<i32, DownloadError> {
+        let minecraft_version = match minecraft_version {
+            Some(minecraft_version) => minecraft_version,
+            None => downloader::get_latest_vanilla_version(http).await?,
+        };
+
+        Ok(self.java_version_for(&MinecraftVersion::parse(&minecraft_version)))
+    }
+
+    /// Returns the Java major version needed to run `minecraft_version` according to the configured thresholds.
+    pub fn java_version_for(&self, minecraft_version: &MinecraftVersion) -> i32 {
+        self.java_version_thresholds().java_version_for(minecraft_version)
+    }
+
+    /// Sets the Java version of every version in `versions` that has one from the configured thresholds, replacing
+    /// the defaults [`VersionInfo::from_id`] uses.
+    pub fn set_java_versions(&self, versions: &mut [VersionInfo]) {
+        let thresholds = self.java_version_thresholds();
+
+        for version in versions.iter_mut().filter(|version| version.java_major.is_some()) {
+            version.java_major = Some(thresholds.java_version_for(&MinecraftVersion::parse(&version.id)));
+        }
+    }
+
+    /// Reads the configured thresholds once, using the default for any that is missing.
+    fn java_version_thresholds(&self) -> JavaVersionThresholds {
         let config = self.get_config();
+        let defaults = JavaVersionThresholds::default();
 
-        let java_version_thresholds = config.get("java_version_thresholds").expect("Failed to get java_version_thresholds");
-
-        Some(java_version_thresholds.get(key).expect("Failed to get java_version key").as_str().expect("Failed to get java_version as string").to_string())
-    }
-
-    pub async fn get_java_version(&self, minecraft_version: Option<String>) -> Option<i32> {
-        let version_index = downloader::version_index(minecraft_version).await.expect("Failed to get version index");
-        let java_21_index = downloader::version_index(Some(self.get_java_version_threshold("java_21".to_string()))
-            .or(Some(Some(self.default_config().java_version_thresholds.java_21.to_string()))).expect("Failed to get default version for Java 21"))
-            .await.expect("Failed to get version index for Java 21");
-        let java_17_index = downloader::version_index(Some(self.get_java_version_threshold("java_17".to_string()))
-            .or(Some(Some(self.default_config().java_version_thresholds.java_17.to_string()))).expect("Failed to get default version for Java 17"))
-            .await.expect("Failed to get version index for Java 17");
-        let java_16_index = downloader::version_index(Some(self.get_java_version_threshold("java_16".to_string()))
-            .or(Some(Some(self.default_config().java_version_thresholds.java_16.to_string()))).expect("Failed to get default version for Java 16"))
-            .await.expect("Failed to get version index for Java 16");
-
-        if version_index >= java_21_index {
-            Some(21)
-        } else if version_index >= java_17_index {
-            Some(17)
-        } else if version_index >= java_16_index {
-            Some(16)
-        } else {
-            Some(8)
+        JavaVersionThresholds {
+            java_16: threshold(&config, "java_16").unwrap_or(defaults.java_16),
+            java_17: threshold(&config, "java_17").unwrap_or(defaults.java_17),
+            java_21: threshold(&config, "java_21").unwrap_or(defaults.java_21),
         }
     }
 
@@ -194,11 +434,10 @@ impl ConfigFile {
                 windows_17: "https://github.com/adoptium/temurin17-binaries/releases/download/jdk-17.0.10%2B7/OpenJDK17U-jre_x64_windows_hotspot_17.0.10_7.zip".to_string(),
                 windows_21: "https://github.com/adoptium/temurin21-binaries/releases/download/jdk-21.0.4%2B7/OpenJDK21U-jre_x64_windows_hotspot_21.0.4_7.zip".to_string(),
             },
-            java_version_thresholds: JavaVersionThresholds {
-                java_16: "21w19a".to_string(),
-                java_17: "1.18-pre2".to_string(),
-                java_21: "24w14a".to_string(),
-            },
+            java_version_thresholds: JavaVersionThresholds::default(),
+            upstreams: BTreeMap::new(),
+            http: HttpSettings::default(),
+            history: HistorySettings::default(),
         }
     }
 }
\ No newline at end of file
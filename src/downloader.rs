@@ -1,36 +1,72 @@
 use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
-use std::cmp::min;
-use std::fs::File;
+use reqwest::{Client, StatusCode};
+use reqwest::header::{CONTENT_RANGE, RANGE, RETRY_AFTER};
+use std::fs;
+use std::fs::OpenOptions;
 use std::io::Write;
 use std::net::{Ipv4Addr, SocketAddrV4};
+use std::path::Path;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 use async_trait::async_trait;
 use public_ip::addr;
+use crate::changelog::BuildChanges;
+use crate::checksum::{file_checksum, Checksum, ChecksumAlgorithm};
 use crate::downloaderror::DownloadError;
+use crate::http::{url_key, Http};
+use crate::installplan::ServerPlan;
+use crate::instance::Instance;
+use crate::minecraftversion::MinecraftVersion;
 use crate::servertype::ServerType;
+use crate::versioninfo::VersionInfo;
 
+/// A server distribution that can be downloaded and installed into the current directory.
 #[async_trait]
 pub trait Installer: Sync {
+    /// The display name, also used to remember which installer set up a server.
     fn get_name(&self) -> String;
+    /// A stable identifier such as `paper`, used on the command line and in `msi-instance.toml`. Unlike the name, it never changes.
+    fn get_id(&self) -> String;
+    /// A one-line description shown in the menu.
     fn get_description(&self) -> String;
     fn get_type(&self) -> ServerType;
+    /// Whether [`Installer::build`] writes its own launch scripts instead of the generated ones.
     fn custom_script(&self) -> bool;
+    /// Whether a Minecraft version has to be chosen. Installers returning `false` always use their latest version.
     fn version_required(&self) -> bool { true }
+    /// The directory plugins or mods go into, if the distribution supports them.
+    fn addon_directory(&self) -> Option<String> { None }
 
-    async fn get_versions(&self, client: Client) -> Vec<String>;
+    /// Returns the versions this distribution can install, oldest first.
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError>;
+    /// Returns the address players can join on if `string`, a line of server output, announces it.
     async fn startup_message(&self, string: String) -> Option<SocketAddrV4>;
-    async fn download(&self, client: Client, minecraft_version: Option<String>) -> Result<String, DownloadError>;
-    async fn build(&self, _java_path: String, _minecraft_version: Option<String>) {}
+    /// Decides what to download for `minecraft_version`, or the latest version if `None`, without downloading anything.
+    async fn resolve(&self, http: Http, minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError>;
+    /// Downloads the artifact chosen by [`Installer::resolve`].
+    async fn download(&self, http: Http, plan: &ServerPlan) -> Result<(), DownloadError> {
+        download_file(&http, &plan.artifact.url, &plan.artifact.path, plan.artifact.checksum.as_ref()).await
+    }
+    /// Runs any post-download installation step with the Java executable at `java_path`.
+    async fn build(&self, _java_path: String, _plan: &ServerPlan) -> Result<(), DownloadError> {
+        Ok(())
+    }
+    /// Returns what changed in each build after the one `installed` describes, up to and including `to` or else the
+    /// newest build of the installed version, oldest first. Distributions without builds use versions instead.
+    /// Returns `None` if the distribution publishes no changelog.
+    async fn changelog(&self, _http: Http, _installed: &Instance, _to: Option<String>) -> Result<Option<Vec<BuildChanges>>, DownloadError> {
+        Ok(None)
+    }
 }
 
+/// Parses the port from the vanilla "Starting Minecraft server on" message and pairs it with the public IP.
 pub async fn basic_server_address_from_string(string: String) -> Option<SocketAddrV4> {
     if string.contains("Starting Minecraft server on *:") {
-        let parsed_port = string.split("*:").collect::<Vec<&str>>()[1].parse::<u16>().expect("Failed to parse port");
+        let parsed_port = string.split("*:").collect::<Vec<&str>>()[1].trim().parse::<u16>().ok()?;
         println!("Port successfully parsed: {}", parsed_port);
 
-        let ipv4: Ipv4Addr = Ipv4Addr::from_str(&addr().await.expect("Failed to get public IP").to_string()).expect("Failed to parse IP");
+        let ipv4: Ipv4Addr = Ipv4Addr::from_str(&addr().await?.to_string()).ok()?;
 
         return Some(SocketAddrV4::new(ipv4, parsed_port));
     }
@@ -38,12 +74,12 @@ pub async fn basic_server_address_from_string(string: String) -> Option<SocketAd
     None
 }
 
+/// Parses the port from a proxy's "Listening on" message and pairs it with the public IP.
 pub async fn basic_proxy_address_from_string(string: String) -> Option<SocketAddrV4> {
     if string.contains("Listening on /") {
         let ip = string.split('/').collect::<Vec<&str>>()[1];
-        let port = ip.split(':').collect::<Vec<&str>>().last().unwrap().parse::<u32>().ok();
-        let ip = &addr().await.expect("Failed to get public IP").to_string();
-        let ip = Ipv4Addr::from_str(ip).ok();
+        let port = ip.split(':').collect::<Vec<&str>>().last()?.trim().parse::<u32>().ok();
+        let ip = addr().await.and_then(|ip| Ipv4Addr::from_str(&ip.to_string()).ok());
 
         if let (Some(ip_value), Some(port_value)) = (ip, port) {
             return Some(SocketAddrV4::new(ip_value, port_value as u16));
@@ -56,72 +92,357 @@ pub async fn basic_proxy_address_from_string(string: String) -> Option<SocketAdd
     None
 }
 
-pub async fn download_file(client: &Client, url: &str, path: &str) -> Result<(), DownloadError> {
-    let request = client.get(url).send().await?;
-    let total_size = request.content_length().unwrap_or(0u64);
+/// Returns up to five versions from `available` that look like a misspelling of `version`, newest first.
+///
+/// Versions within a few edits of `version` are preferred. If there are none, the versions sharing
+/// the longest prefix with it are returned instead.
+pub fn closest_versions(version: &str, available: &[String]) -> Vec<String> {
+    let max_distance = (version.chars().count() / 3).max(1);
+    let mut close = available
+        .iter()
+        .map(|candidate| (edit_distance(version, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect::<Vec<(usize, &String)>>();
+
+    if !close.is_empty() {
+        close.sort_by(|(a_distance, a), (b_distance, b)| a_distance.cmp(b_distance).then_with(|| MinecraftVersion::parse(b).cmp(&MinecraftVersion::parse(a))));
+
+        return close.into_iter().take(5).map(|(_, candidate)| candidate.to_string()).collect();
+    }
+
+    let common_prefix = |candidate: &String| candidate.chars().zip(version.chars()).take_while(|(a, b)| a == b).count();
+    let longest = available.iter().map(common_prefix).max().unwrap_or(0);
+
+    if longest == 0 {
+        return vec![];
+    }
+
+    let closest = available.iter().filter(|candidate| common_prefix(candidate) == longest).cloned().collect::<Vec<String>>();
+    closest[closest.len().saturating_sub(5)..].iter().rev().cloned().collect()
+}
+
+/// The number of single character insertions, deletions and substitutions needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
 
-    let progress_bar = ProgressBar::new(total_size);
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Fetches a checksum published next to `url`, e.g. the `.sha1` files of Maven repositories. Returns `None` if there is none.
+pub async fn get_published_checksum(http: &Http, url: &str, suffix: &str, algorithm: ChecksumAlgorithm) -> Result<Option<Checksum>, DownloadError> {
+    match http.get_text(&format!("{}{}", url, suffix)).await {
+        Ok(body) => Ok(body.split_whitespace().next().map(|value| Checksum::new(algorithm, value))),
+        Err(DownloadError::Http { status: StatusCode::NOT_FOUND, .. }) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// How many times a download is attempted before giving up.
+const DOWNLOAD_ATTEMPTS: u32 = 5;
+/// The longest we wait between attempts, even if the server asks for more with `Retry-After`.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Why a download attempt stopped, and whether and when to try again.
+struct AttemptError {
+    error: DownloadError,
+    retry: bool,
+    retry_after: Option<Duration>,
+}
+
+/// Downloads `url` to `path`, taking it from the artifact cache if it was downloaded before.
+///
+/// New downloads are added to the cache. If `checksum` is given, the file has to match it. When
+/// offline, only cached artifacts can be used.
+pub async fn download_file(http: &Http, url: &str, path: &str, checksum: Option<&Checksum>) -> Result<(), DownloadError> {
+    if http.artifact_path().is_none() {
+        if http.is_offline() {
+            return Err(DownloadError::NotCached { url: url.to_string() });
+        }
+
+        return fetch_file(http, url, path, checksum).await;
+    }
+
+    let blob_path = cache_artifact(http, url, checksum).await?;
+    let part_path = format!("{}.part", path);
+
+    fs::copy(&blob_path, &part_path).map_err(|error| DownloadError::io(&part_path, error))?;
+    fs::rename(&part_path, path).map_err(|error| DownloadError::io(path, error))
+}
+
+/// Makes sure the artifact at `url` is in the artifact cache, downloading it if needed, and returns where it is.
+pub async fn cache_artifact(http: &Http, url: &str, checksum: Option<&Checksum>) -> Result<String, DownloadError> {
+    if let Some(blob_path) = http.cached_artifact(url, checksum) {
+        http.progress().suspend(|| println!("Using cached {}.", url));
+        return Ok(blob_path);
+    }
+
+    let artifact_path = match http.artifact_path() {
+        Some(artifact_path) if !http.is_offline() => artifact_path,
+        _ => return Err(DownloadError::NotCached { url: url.to_string() }),
+    };
+
+    fs::create_dir_all(&artifact_path).map_err(|error| DownloadError::io(&artifact_path, error))?;
+
+    let download_path = format!("{}/{}.download", artifact_path, url_key(url));
+    fetch_file(http, url, &download_path, checksum).await?;
+
+    http.store_artifact(url, &download_path)
+}
+
+/// Downloads `url` to `path` while showing a progress bar.
+///
+/// The file is written to `path` with a `.part` suffix and only moved into place once it is complete
+/// and, if `checksum` is given, matches it. Dropped connections and rate limits are retried with
+/// exponential backoff, resuming where the previous attempt, or a previous run, stopped. If a download keeps failing,
+/// the next mirror configured for `url` is tried, see [`Http::mirrors`]. On a checksum mismatch the
+/// file is deleted and [`DownloadError::ChecksumMismatch`] returned.
+async fn fetch_file(http: &Http, url: &str, path: &str, checksum: Option<&Checksum>) -> Result<(), DownloadError> {
+    let part_path = format!("{}.part", path);
+    let mirrors = http.mirrors(url);
+
+    let progress_bar = http.progress().add(ProgressBar::new(0));
     progress_bar.set_style(ProgressStyle::default_bar()
         .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.green/white}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})").expect("Failed to set progress bar style")
         .progress_chars("#>-"));
 
-    let mut file = File::create(path)?;
-    let mut download_progress: u64 = 0;
-    let mut stream = request.bytes_stream();
+    for (index, mirror) in mirrors.iter().enumerate() {
+        // A part left by an earlier run is resumed from the first mirror, but mirrors may serve different bytes.
+        if index > 0 && Path::new(&part_path).exists() {
+            fs::remove_file(&part_path).map_err(|error| DownloadError::io(&part_path, error))?;
+        }
 
-    while let Some(item) = stream.next().await {
-        let chunk = item?;
-        file.write_all(&chunk)?;
+        match fetch_from(http.client(), mirror, &part_path, checksum, &progress_bar).await {
+            Ok(()) => {
+                progress_bar.finish_with_message(format!("Downloaded {} to {}.", mirror, path));
 
-        let new = min(download_progress + (chunk.len() as u64), total_size);
+                return fs::rename(&part_path, path).map_err(|error| DownloadError::io(path, error));
+            }
+            Err(error) if index + 1 < mirrors.len() => progress_bar.suspend(|| eprintln!("Warning: {}. Trying {} instead.", error, mirrors[index + 1])),
+            Err(error) => {
+                progress_bar.abandon();
+                return Err(error);
+            }
+        }
+    }
+
+    unreachable!("Http::mirrors always returns at least one URL")
+}
 
-        download_progress = new;
-        progress_bar.set_position(new);
+/// Downloads `url` into `part_path`, retrying failed attempts, and checks it against `checksum`.
+async fn fetch_from(client: &Client, url: &str, part_path: &str, checksum: Option<&Checksum>, progress_bar: &ProgressBar) -> Result<(), DownloadError> {
+    let mut attempt = 1;
+
+    while let Err(failure) = download_attempt(client, url, part_path, progress_bar).await {
+        if !failure.retry || attempt >= DOWNLOAD_ATTEMPTS {
+            return Err(failure.error);
+        }
+
+        let delay = failure.retry_after.unwrap_or(Duration::from_secs(1 << (attempt - 1))).min(MAX_RETRY_DELAY);
+        progress_bar.set_message(format!("{}, retrying in {} seconds...", failure.error, delay.as_secs()));
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
     }
 
-    progress_bar.finish_with_message(format!("Downloaded {} to {}.", url, path));
+    if let Some(expected) = checksum {
+        let actual = file_checksum(part_path, expected.algorithm).map_err(|error| DownloadError::io(part_path, error))?;
+
+        if &actual != expected {
+            fs::remove_file(part_path).map_err(|error| DownloadError::io(part_path, error))?;
+
+            return Err(DownloadError::ChecksumMismatch { url: url.to_string(), expected: expected.to_string(), actual: actual.to_string() });
+        }
+    }
 
     Ok(())
 }
 
-pub async fn version_index(mut minecraft_version: Option<String>) -> Result<i32, DownloadError> {
-    let manifest_url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
-    let manifest_body = reqwest::get(manifest_url).await?.text().await?;
-    let manifest_json: serde_json::Value = serde_json::from_str(&manifest_body).expect("Failed to parse manifest JSON");
+/// Downloads the rest of `url` into `part_path`, asking only for the bytes it does not have yet.
+async fn download_attempt(client: &Client, url: &str, part_path: &str, progress_bar: &ProgressBar) -> Result<(), AttemptError> {
+    let retry = |error: DownloadError| AttemptError { error, retry: true, retry_after: None };
+    let fail = |error: DownloadError| AttemptError { error, retry: false, retry_after: None };
+
+    let existing = fs::metadata(part_path).map(|metadata| metadata.len()).unwrap_or(0);
+    let mut request = client.get(url);
 
-    if minecraft_version.is_none() {
-        minecraft_version = Some(get_latest_vanilla_version().await?);
+    if existing > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing));
     }
 
-    let version_array: Vec<&serde_json::Value> = manifest_json
-        .get("versions")
-        .expect("Failed to get versions")
-        .as_array()
-        .expect("Failed to get versions as array")
-        .iter().rev()
-        .collect();
+    let response = request.send().await.map_err(|error| retry(error.into()))?;
+    let status = response.status();
 
-    let version_index = version_array
-        .iter()
-        .position(|version| version["id"].as_str().expect("Failed to get ID") == minecraft_version.clone().unwrap())
-        .expect("Failed to get selected version") as i32;
+    if status == StatusCode::RANGE_NOT_SATISFIABLE && existing > 0 {
+        let total = response.headers().get(CONTENT_RANGE).and_then(|range| range.to_str().ok()).and_then(|range| range.strip_prefix("bytes */"));
+
+        if total == Some(existing.to_string().as_str()) {
+            return Ok(());
+        }
+
+        fs::remove_file(part_path).map_err(|error| fail(DownloadError::io(part_path, error)))?;
+        return Err(retry(DownloadError::Http { url: url.to_string(), status }));
+    }
+
+    if !status.is_success() {
+        let retry_after = response.headers().get(RETRY_AFTER).and_then(|value| value.to_str().ok()).and_then(parse_retry_after);
+
+        return Err(AttemptError {
+            error: DownloadError::Http { url: url.to_string(), status },
+            retry: status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            retry_after,
+        });
+    }
+
+    let resumed = status == StatusCode::PARTIAL_CONTENT;
+
+    if resumed {
+        let start = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|range| range.to_str().ok())
+            .and_then(|range| range.strip_prefix("bytes "))
+            .and_then(|range| range.split_once('-'))
+            .and_then(|(start, _)| start.trim().parse::<u64>().ok());
 
-    Ok(version_index)
+        if start != Some(existing) {
+            fs::remove_file(part_path).map_err(|error| fail(DownloadError::io(part_path, error)))?;
+            return Err(retry(DownloadError::schema(url, "resumed download does not start where the previous one stopped")));
+        }
+    }
+
+    let expected = response.content_length();
+    let mut received = 0;
+    let mut download_progress = if resumed { existing } else { 0 };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(part_path)
+        .map_err(|error| fail(DownloadError::io(part_path, error)))?;
+
+    progress_bar.set_length(download_progress + response.content_length().unwrap_or(0));
+    progress_bar.set_position(download_progress);
+    progress_bar.set_message(format!("Downloading {}...", url));
+
+    let mut stream = response.bytes_stream();
+
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|error| retry(error.into()))?;
+        file.write_all(&chunk).map_err(|error| fail(DownloadError::io(part_path, error)))?;
+
+        received += chunk.len() as u64;
+        download_progress += chunk.len() as u64;
+        progress_bar.set_position(download_progress);
+    }
+
+    match expected {
+        Some(expected) if received != expected => Err(retry(DownloadError::Incomplete { url: url.to_string(), expected, received })),
+        _ => Ok(()),
+    }
 }
 
-pub async fn get_latest_vanilla_version() -> Result<String, DownloadError> {
+/// Reads a `Retry-After` header, which is either a number of seconds or an HTTP date. A date in the past means no wait.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = httpdate::parse_http_date(value).ok()?;
+            Some(date.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+        }
+    }
+}
+
+/// Returns the latest Minecraft release.
+pub async fn get_latest_vanilla_version(http: &Http) -> Result<String, DownloadError> {
     let manifest_url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
-    let manifest_body = reqwest::get(manifest_url).await?.text().await?;
-    let manifest_json: serde_json::Value = serde_json::from_str(&manifest_body).expect("Failed to parse manifest JSON");
+    let manifest_json = http.get_json(manifest_url).await?;
 
     let latest_version = manifest_json
         .get("latest")
-        .expect("Failed to get latest release version")
-        .get("release")
-        .expect("Failed to get latest release version")
-        .as_str()
-        .expect("Failed to get latest release version as string")
+        .and_then(|latest| latest.get("release"))
+        .and_then(|release| release.as_str())
+        .ok_or_else(|| DownloadError::schema(manifest_url, "missing latest release version"))?
         .to_string();
 
     Ok(latest_version)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("", "1.20"), 4);
+        assert_eq!(edit_distance("1.20", ""), 4);
+        assert_eq!(edit_distance("1.20.4", "1.20.4"), 0);
+        assert_eq!(edit_distance("1.20.4", "1.20.5"), 1);
+        assert_eq!(edit_distance("1.20", "1.20.4"), 2);
+        // A transposition is two substitutions, not one edit.
+        assert_eq!(edit_distance("1.02", "1.20"), 2);
+    }
+
+    #[test]
+    fn closest_versions_are_ordered_by_distance_then_newest_first() {
+        let available = ids(&["1.19.4", "1.20.1", "1.20.2", "1.20.4", "1.21"]);
+
+        assert_eq!(closest_versions("1.20.9", &available), ids(&["1.20.4", "1.20.2", "1.20.1"]));
+        assert_eq!(closest_versions("1.20.3", &available)[0], "1.20.4");
+    }
+
+    #[test]
+    fn closest_versions_stop_at_the_cutoff() {
+        let available = ids(&["1.8.9", "1.12.2", "1.16.5", "1.20.4"]);
+
+        assert_eq!(closest_versions("2", &available), Vec::<String>::new());
+        // 1.20.4 is three edits away, more than the two allowed for six characters.
+        assert_eq!(closest_versions("1.12.9", &available), ids(&["1.12.2", "1.16.5", "1.8.9"]));
+    }
+
+    #[test]
+    fn closest_versions_fall_back_to_the_longest_common_prefix() {
+        let available = ids(&["1.20.1", "1.20.2", "1.20.3", "1.20.4", "1.20.5", "1.20.6"]);
+
+        assert_eq!(closest_versions("1.20.4-pre99", &available), ids(&["1.20.4"]));
+        assert_eq!(closest_versions("1.20-anything", &available), ids(&["1.20.6", "1.20.5", "1.20.4", "1.20.3", "1.20.2"]));
+    }
+
+    #[test]
+    fn closest_versions_suggest_nearby_snapshots() {
+        let available = ids(&["1.20.4", "23w14a", "24w13a", "24w14a", "24w14potato"]);
+
+        assert_eq!(closest_versions("24w14b", &available), ids(&["24w14a", "24w13a", "23w14a"]));
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_dates() {
+        let in_a_minute = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let waited = parse_retry_after(&in_a_minute).unwrap();
+
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert!(waited > Duration::from_secs(55) && waited <= Duration::from_secs(60));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
@@ -1,19 +1,102 @@
-use reqwest::Error;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use reqwest::StatusCode;
 
+/// Why downloading or installing a server failed.
 #[derive(Debug)]
 pub enum DownloadError {
-    Success,
-    Failure,
+    /// The upstream answered with a non-success status code.
+    Http { url: String, status: StatusCode },
+    /// The request could not be sent or its response could not be read.
+    Network(reqwest::Error),
+    /// Reading or writing a local file failed.
+    Io { path: String, source: std::io::Error },
+    /// The requested version is not available, with the closest versions that are, newest first.
+    VersionNotFound { version: String, suggestions: Vec<String> },
+    /// An upstream response did not have the shape we expect, usually because its API changed.
+    Schema { url: String, message: String },
+    /// The connection closed before the whole file was received.
+    Incomplete { url: String, expected: u64, received: u64 },
+    /// A downloaded file does not match the checksum published for it.
+    ChecksumMismatch { url: String, expected: String, actual: String },
+    /// An installer process, such as the Forge installer, did not finish successfully.
+    InstallerFailed { installer: String, status: Option<i32> },
+    /// We are offline and `url` has not been cached.
+    NotCached { url: String },
+    /// A file we were asked to install from, such as the lockfile, is invalid or does not fit this machine.
+    InvalidFile { path: String, message: String },
+    /// No instance with this name exists, see [`crate::registry::Registry`].
+    InstanceNotFound { name: String },
+    /// An instance with this name already exists.
+    InstanceExists { name: String },
+    /// The name cannot be used for an instance.
+    InvalidInstanceName { name: String },
 }
 
-impl From<Error> for DownloadError {
-    fn from(_: Error) -> Self {
-        DownloadError::Failure
+impl DownloadError {
+    pub fn io(path: &str, source: std::io::Error) -> DownloadError {
+        DownloadError::Io { path: path.to_string(), source }
+    }
+
+    pub fn schema(url: &str, message: &str) -> DownloadError {
+        DownloadError::Schema { url: url.to_string(), message: message.to_string() }
+    }
+
+    pub fn version_not_found(version: &str, suggestions: Vec<String>) -> DownloadError {
+        DownloadError::VersionNotFound { version: version.to_string(), suggestions }
+    }
+
+    pub fn invalid_file(path: &str, message: &str) -> DownloadError {
+        DownloadError::InvalidFile { path: path.to_string(), message: message.to_string() }
+    }
+}
+
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadError::Http { url, status } => write!(f, "{} returned {}", url, status),
+            DownloadError::Network(error) => match error.url() {
+                Some(url) => write!(f, "Could not reach {}: {}", url, error),
+                None => write!(f, "Network error: {}", error),
+            },
+            DownloadError::Io { path, source } => write!(f, "Could not access {}: {}", path, source),
+            DownloadError::VersionNotFound { version, suggestions } => {
+                write!(f, "Version {} was not found", version)?;
+
+                if !suggestions.is_empty() {
+                    write!(f, ", did you mean {}?", suggestions.join(", "))?;
+                }
+
+                Ok(())
+            }
+            DownloadError::Schema { url, message } => write!(f, "Unexpected response from {}: {}", url, message),
+            DownloadError::Incomplete { url, expected, received } => write!(f, "Download of {} ended after {} of {} bytes", url, received, expected),
+            DownloadError::ChecksumMismatch { url, expected, actual } => write!(f, "Checksum of {} does not match, expected {} but got {}", url, expected, actual),
+            DownloadError::InstallerFailed { installer, status } => match status {
+                Some(status) => write!(f, "{} exited with code {}", installer, status),
+                None => write!(f, "{} was terminated", installer),
+            },
+            DownloadError::NotCached { url } => write!(f, "{} is not cached, run prefetch while online first", url),
+            DownloadError::InvalidFile { path, message } => write!(f, "{} cannot be used: {}", path, message),
+            DownloadError::InstanceNotFound { name } => write!(f, "There is no instance called {}", name),
+            DownloadError::InstanceExists { name } => write!(f, "An instance called {} already exists", name),
+            DownloadError::InvalidInstanceName { name } => write!(f, "{} is not a valid instance name, use only letters, digits, '-', '_' and '.'", name),
+        }
+    }
+}
+
+impl Error for DownloadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DownloadError::Network(error) => Some(error),
+            DownloadError::Io { source, .. } => Some(source),
+            _ => None,
+        }
     }
 }
 
-impl From<std::io::Error> for DownloadError {
-    fn from(_: std::io::Error) -> Self {
-        DownloadError::Failure
+impl From<reqwest::Error> for DownloadError {
+    fn from(error: reqwest::Error) -> Self {
+        DownloadError::Network(error)
     }
 }
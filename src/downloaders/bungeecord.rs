@@ -1,9 +1,13 @@
 use async_trait::async_trait;
-use crate::downloader::{basic_proxy_address_from_string, download_file, Installer};
+use crate::downloader::{basic_proxy_address_from_string, Installer};
+use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::installplan::{Artifact, ServerPlan};
 use crate::servertype::ServerType;
+use crate::versioninfo::VersionInfo;
 use crate::servertype::ServerType::Proxy;
 
-pub(crate) struct BungeeCord {}
+pub struct BungeeCord {}
 
 #[async_trait]
 impl Installer for BungeeCord {
@@ -11,6 +15,10 @@ impl Installer for BungeeCord {
         "BungeeCord".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "bungeecord".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A server that supports BungeeCord plugins.".to_string()
     }
@@ -23,23 +31,38 @@ impl Installer for BungeeCord {
         false
     }
 
+    fn addon_directory(&self) -> Option<String> {
+        Some("plugins".to_string())
+    }
+
     fn version_required(&self) -> bool {
         false
     }
 
-    async fn get_versions(&self, client: reqwest::Client) -> Vec<String> {
-        let version = client.get("https://ci.md-5.net/job/BungeeCord/lastSuccessfulBuild/buildNumber").send().await.expect("Failed to get latest version for ").text();
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
+        let version = http.get_text("https://ci.md-5.net/job/BungeeCord/lastSuccessfulBuild/buildNumber").await?;
 
-        vec![version.await.unwrap_or("".to_string())]
+        Ok(vec![VersionInfo { java_major: None, ..VersionInfo::from_id(version.trim()) }])
     }
 
     async fn startup_message(&self, string: String) -> Option<std::net::SocketAddrV4> {
         basic_proxy_address_from_string(string).await
     }
 
-    async fn download(&self, client: reqwest::Client, _minecraft_version: Option<String>) -> Result<String, crate::downloaderror::DownloadError> {
-        download_file(&client, "https://ci.md-5.net/job/BungeeCord/lastSuccessfulBuild/artifact/bootstrap/target/BungeeCord.jar", "./server.jar").await?;
+    async fn resolve(&self, http: Http, _minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let build = http.get_text("https://ci.md-5.net/job/BungeeCord/lastSuccessfulBuild/buildNumber").await?;
+        let build = build.trim();
 
-        Ok("".to_string())
+        Ok(ServerPlan {
+            minecraft_version: None,
+            version: None,
+            build: Some(build.to_string()),
+            artifact: Artifact {
+                url: format!("https://ci.md-5.net/job/BungeeCord/{}/artifact/bootstrap/target/BungeeCord.jar", build),
+                path: "./server.jar".to_string(),
+                checksum: None,
+            },
+            files: vec!["server.jar".to_string()],
+        })
     }
 }
\ No newline at end of file
@@ -1,13 +1,18 @@
-use reqwest::Client;
-use std::error::Error;
 use std::net::SocketAddrV4;
 use async_trait::async_trait;
-use crate::downloader::{basic_server_address_from_string, download_file, Installer};
+use reqwest::StatusCode;
+use crate::downloader::{basic_server_address_from_string, closest_versions, Installer};
 use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::changelog::{select_builds, BuildChanges};
+use crate::installplan::{Artifact, ServerPlan};
+use crate::instance::Instance;
+use crate::minecraftversion::MinecraftVersion;
 use crate::servertype::ServerType;
+use crate::versioninfo::VersionInfo;
 use crate::servertype::ServerType::Server;
 
-pub(crate) struct Fabric {}
+pub struct Fabric {}
 
 #[async_trait]
 impl Installer for Fabric {
@@ -15,6 +20,10 @@ impl Installer for Fabric {
         "Fabric".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "fabric".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A server that supports Fabric mods.".to_string()
     }
@@ -27,49 +36,85 @@ impl Installer for Fabric {
         false
     }
 
-    async fn get_versions(&self, client: Client) -> Vec<String> {
-        let json: serde_json::Value = client.get("https://meta.fabricmc.net/v2/versions/game").send().await.expect("Failed to get latest version for Fabric").json().await.expect("Failed to get latest version for Fabric");
+    fn addon_directory(&self) -> Option<String> {
+        Some("mods".to_string())
+    }
+
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
+        let url = "https://meta.fabricmc.net/v2/versions/game";
+        let json = http.get_json(url).await?;
 
-        let versions: Vec<String> = json.as_array().map(|versions| {
-            versions.iter().map(|version| version["version"].as_str().unwrap().to_string()).collect()
-        }).unwrap_or_default();
+        let versions = json
+            .as_array()
+            .ok_or_else(|| DownloadError::schema(url, "expected an array of game versions"))?
+            .iter()
+            .rev()
+            .filter_map(|version| {
+                let id = version["version"].as_str()?;
+
+                Some(VersionInfo {
+                    stable: version["stable"].as_bool().unwrap_or(false),
+                    ..VersionInfo::from_id(id)
+                })
+            })
+            .collect::<Vec<VersionInfo>>();
 
-        versions
+        Ok(versions)
     }
 
     async fn startup_message(&self, string: String) -> Option<SocketAddrV4> {
         basic_server_address_from_string(string).await
     }
 
-    async fn download(&self, client: Client, minecraft_version: Option<String>) -> Result<String, DownloadError> {
-        let fabric_version = get_latest_fabric_version(&minecraft_version).await.expect("Failed to get latest fabric version");
-        let fabric_build = get_fabric_build().await.expect("Failed to get latest fabric build");
-
-        println!(
-            "Using game version {} with Fabric build {}.",
-            fabric_version, fabric_build
-        );
+    async fn resolve(&self, http: Http, minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let fabric_version = get_latest_fabric_version(&http, &minecraft_version).await?;
+        let fabric_build = get_fabric_build(&http).await?;
 
         let url = format!(
             "https://meta.fabricmc.net/v2/versions/loader/{}/{}/1.0.0/server/jar",
             fabric_version, fabric_build
         );
 
-        download_file(&client, &url, "./server.jar").await?;
+        Ok(ServerPlan {
+            minecraft_version: Some(fabric_version),
+            version: Some(fabric_build),
+            build: None,
+            artifact: Artifact {
+                url,
+                path: "./server.jar".to_string(),
+                checksum: None,
+            },
+            files: vec!["server.jar".to_string()],
+        })
+    }
+
+    async fn changelog(&self, http: Http, installed_server: &Instance, to: Option<String>) -> Result<Option<Vec<BuildChanges>>, DownloadError> {
+        let Some(releases) = get_loader_releases(&http).await? else {
+            return Ok(None);
+        };
 
-        Ok(fabric_version.to_string())
+        Ok(Some(select_builds(releases, |release| &release.build, installed_server.version.as_deref(), to.as_deref())))
     }
 }
 
-async fn get_latest_fabric_version(minecraft_version: &Option<String>) -> Result<String, Box<dyn Error>> {
+async fn get_latest_fabric_version(http: &Http, minecraft_version: &Option<String>) -> Result<String, DownloadError> {
     let url = "https://meta.fabricmc.net/v2/versions";
-    let response = reqwest::get(url).await?;
-    let json: serde_json::Value = response.json().await?;
+    let json = http.get_json(url).await?;
 
-    let game_versions = json["game"].as_array().ok_or("Invalid JSON format")?;
+    let game_versions = json["game"].as_array().ok_or_else(|| DownloadError::schema(url, "missing game versions"))?;
 
-    if minecraft_version.is_none() {
-        let stable_game_version = game_versions
+    if let Some(minecraft_version) = minecraft_version {
+        let versions = game_versions
+            .iter()
+            .filter_map(|version| version["version"].as_str().map(|v| v.to_string()))
+            .collect::<Vec<String>>();
+
+        let stable_game_version = MinecraftVersion::resolve(minecraft_version, &versions)
+            .ok_or_else(|| DownloadError::version_not_found(minecraft_version, closest_versions(minecraft_version, &versions)))?;
+
+        Ok(stable_game_version.to_string())
+    } else {
+        let stable_versions = game_versions
             .iter()
             .filter_map(|version| {
                 let is_stable = version["stable"].as_bool()?;
@@ -79,32 +124,18 @@ async fn get_latest_fabric_version(minecraft_version: &Option<String>) -> Result
                     None
                 }
             })
-            .max()
-            .ok_or("No stable game version found")?;
+            .collect::<Vec<String>>();
 
-        Ok(stable_game_version)
-    } else {
-        let stable_game_version = game_versions
-            .iter()
-            .filter_map(|version| {
-                let version = version["version"].as_str()?;
-                if version.eq(minecraft_version.clone().unwrap().as_str()) {
-                    Some(version)
-                } else {
-                    None
-                }
-            })
-            .max()
-            .ok_or("Version not found!")?;
+        let stable_game_version = MinecraftVersion::latest(&stable_versions)
+            .ok_or_else(|| DownloadError::schema(url, "no stable game version listed"))?;
 
         Ok(stable_game_version.to_string())
     }
 }
 
-async fn get_fabric_build() -> Result<String, Box<dyn Error>> {
+async fn get_fabric_build(http: &Http) -> Result<String, DownloadError> {
     let url = "https://meta.fabricmc.net/v2/versions/loader";
-    let response = reqwest::get(url).await?;
-    let json: serde_json::Value = response.json().await?;
+    let json = http.get_json(url).await?;
 
     let stable_fabric_version = json
         .as_array()
@@ -114,7 +145,49 @@ async fn get_fabric_build() -> Result<String, Box<dyn Error>> {
                 .find(|version| version["stable"].as_bool() == Some(true))
         })
         .and_then(|version| version["version"].as_str())
-        .ok_or("No stable fabric version found")?;
+        .ok_or_else(|| DownloadError::schema(url, "no stable loader version listed"))?;
 
     Ok(stable_fabric_version.to_string())
 }
+
+/// Reads the loader's release notes from GitHub, where Fabric publishes them. Only list items are kept as changes.
+///
+/// Returns `None` if GitHub refuses the request, which it does once the rate limit for unauthenticated clients is used up.
+async fn get_loader_releases(http: &Http) -> Result<Option<Vec<BuildChanges>>, DownloadError> {
+    let url = "https://api.github.com/repos/FabricMC/fabric-loader/releases?per_page=100";
+
+    let pages = match http.get_json_pages(url, "application/vnd.github+json").await {
+        Ok(pages) => pages,
+        Err(DownloadError::Http { status, .. }) if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS => {
+            http.progress().suspend(|| eprintln!("Warning: GitHub refused to list Fabric Loader releases ({}), most likely because of its rate limit. Try again later.", status));
+            return Ok(None);
+        }
+        Err(error) => return Err(error),
+    };
+
+    let mut releases = vec![];
+
+    for page in &pages {
+        let page = page.as_array().ok_or_else(|| DownloadError::schema(url, "missing releases array"))?;
+
+        releases.extend(page.iter().filter_map(|release| {
+            let changes = release["body"]
+                .as_str()
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("- ").or_else(|| line.trim().strip_prefix("* ")))
+                .map(|change| change.trim().to_string())
+                .collect();
+
+            Some(BuildChanges {
+                build: release["tag_name"].as_str()?.to_string(),
+                date: release["published_at"].as_str().and_then(|time| time.get(..10)).map(|date| date.to_string()),
+                channel: None,
+                experimental: release["prerelease"].as_bool().unwrap_or(false),
+                changes,
+            })
+        }));
+    }
+
+    Ok(Some(releases))
+}
@@ -1,18 +1,20 @@
-use std::error::Error;
 use std::fs;
 use std::fs::File;
 use std::net::SocketAddrV4;
 use std::process::Command;
 use async_trait::async_trait;
-use reqwest::Client;
 use semver::Version;
-use serde_json::Value;
-use crate::downloader::{basic_server_address_from_string, Installer};
+use crate::downloader::{basic_server_address_from_string, closest_versions, get_published_checksum, Installer};
 use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::checksum::ChecksumAlgorithm;
+use crate::installplan::{Artifact, ServerPlan};
+use crate::minecraftversion::MinecraftVersion;
 use crate::servertype::ServerType;
+use crate::versioninfo::VersionInfo;
 use crate::servertype::ServerType::Server;
 
-pub(crate) struct Forge {}
+pub struct Forge {}
 
 #[async_trait]
 impl Installer for Forge {
@@ -20,6 +22,10 @@ impl Installer for Forge {
         "Forge".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "forge".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A server that supports Forge mods.".to_string()
     }
@@ -32,88 +38,99 @@ impl Installer for Forge {
         false
     }
 
-    async fn get_versions(&self, client: Client) -> Vec<String> {
+    fn addon_directory(&self) -> Option<String> {
+        Some("mods".to_string())
+    }
+
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
         let url = "https://files.minecraftforge.net/maven/net/minecraftforge/forge/promotions_slim.json";
-        let response = client.get(url).send().await.expect("Failed to get latest version for Forge");
-        let json: Value = response.json().await.expect("Failed to get latest version for Forge");
+        let json = http.get_json(url).await?;
+
+        let game_versions = json["promos"].as_object().ok_or_else(|| DownloadError::schema(url, "missing promos"))?;
 
-        let game_versions = json["promos"].as_object().expect("Invalid JSON format");
+        let mut versions: Vec<VersionInfo> = vec![];
 
-        let versions: Vec<String> = game_versions
-            .iter()
-            .map(|(version, _)| version.to_string().replace("-latest", "").replace("-recommended", ""))
-            .collect();
+        for promotion in game_versions.keys() {
+            let id = promotion.replace("-latest", "").replace("-recommended", "");
+            let recommended = game_versions.contains_key(&format!("{}-recommended", id));
 
-        versions
+            if !versions.iter().any(|version| version.id == id) {
+                versions.push(VersionInfo {
+                    stable: recommended,
+                    ..VersionInfo::from_id(&id)
+                });
+            }
+        }
+
+        Ok(versions)
     }
 
     async fn startup_message(&self, string: String) -> Option<SocketAddrV4> {
         basic_server_address_from_string(string).await
     }
 
-    async fn download(&self, client: Client, mut minecraft_version: Option<String>) -> Result<String, DownloadError> {
-        let forge_version = get_forge_build(minecraft_version.clone()).await.expect("Failed to get latest forge version");
-
-        if minecraft_version.is_none() {
-            minecraft_version = Some(get_latest_forge_version().await.expect("Failed to get latest forge version"));
-        }
+    async fn resolve(&self, http: Http, minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let minecraft_version = get_forge_minecraft_version(&http, minecraft_version).await?;
+        let forge_version = get_forge_build(&http, &minecraft_version).await?;
 
-        println!(
-            "Using game version {} with Forge version {}.",
-            minecraft_version.clone().unwrap_or("".to_string()),
-            forge_version
-        );
-
-        let url = if minecraft_version.clone().unwrap().split('.').collect::<Vec<&str>>().get(1).unwrap().eq(&"7") ||
-            minecraft_version.eq(&Some("1.8.9".to_string())) { // These two are the only versions that have a different URL format
+        let url = if minecraft_version.split('.').nth(1) == Some("7") || minecraft_version == "1.8.9" { // These two are the only versions that have a different URL format
             format!(
                 "https://files.minecraftforge.net/maven/net/minecraftforge/forge/{}-{}-{}/forge-{}-{}-{}-installer.jar",
-                minecraft_version.clone().unwrap_or("".to_string()),
+                minecraft_version,
                 forge_version,
-                minecraft_version.clone().unwrap_or("".to_string()),
-                minecraft_version.clone().unwrap_or("".to_string()),
+                minecraft_version,
+                minecraft_version,
                 forge_version,
-                minecraft_version.clone().unwrap_or("".to_string()),
+                minecraft_version,
             )
         } else {
             format!(
                 "https://files.minecraftforge.net/maven/net/minecraftforge/forge/{}-{}/forge-{}-{}-installer.jar",
-                minecraft_version.clone().unwrap_or("".to_string()),
+                minecraft_version,
                 forge_version,
-                minecraft_version.clone().unwrap_or("".to_string()),
+                minecraft_version,
                 forge_version
             )
         };
 
-        crate::downloader::download_file(&client, &url, "./forge.jar").await?;
-
-        Ok(minecraft_version.clone().expect("Failed to get minecraft version").to_string())
+        let sha1 = get_published_checksum(&http, &url, ".sha1", ChecksumAlgorithm::Sha1).await?;
+
+        Ok(ServerPlan {
+            minecraft_version: Some(minecraft_version),
+            version: Some(forge_version),
+            build: None,
+            artifact: Artifact {
+                url,
+                path: "./forge.jar".to_string(),
+                checksum: sha1,
+            },
+            files: vec!["server.jar".to_string(), "libraries/".to_string()],
+        })
     }
 
-    async fn build(&self, java_path: String, mut minecraft_version: Option<String>) {
+    async fn build(&self, java_path: String, plan: &ServerPlan) -> Result<(), DownloadError> {
         let mut command = Command::new(java_path);
+        let minecraft_version = plan.minecraft_version.clone().unwrap_or_default();
+        let forge_version = plan.version.clone().unwrap_or_default();
 
-        if minecraft_version.is_none() {
-            minecraft_version = Some(get_latest_forge_version().await.expect("Failed to get latest forge version"));
-        }
+        let installer_size = fs::metadata("forge.jar").map_err(|error| DownloadError::io("forge.jar", error))?.len();
 
-        if fs::metadata("forge.jar").unwrap().len() < 1000 {
-            fs::remove_file("forge.jar").expect("Failed to remove forge jar");
-            panic!("Forge version not found.");
+        if installer_size < 1000 {
+            fs::remove_file("forge.jar").map_err(|error| DownloadError::io("forge.jar", error))?;
+            return Err(DownloadError::version_not_found(&minecraft_version, vec![]));
         }
 
-        let mut process = command
+        let status = command
             .arg("-jar")
             .arg("forge.jar")
             .arg("--installServer")
             .arg(".")
-            .spawn()
-            .expect("Failed to build server");
-
-        process.wait().expect("Failed to build server");
+            .status()
+            .map_err(|error| DownloadError::io("forge.jar", error))?;
 
-        let forge_version = get_forge_build(minecraft_version.clone()).await.expect("Failed to get latest forge version");
-        let minecraft_version = minecraft_version.unwrap();
+        if !status.success() {
+            return Err(DownloadError::InstallerFailed { installer: "Forge installer".to_string(), status: status.code() });
+        }
 
         println!("Building server with Forge version {}. This will take a while...", minecraft_version);
 
@@ -134,68 +151,81 @@ impl Installer for Forge {
 
         println!("Renaming server {} to server.jar...", file_name);
 
-        fs::rename(format!("./{}", file_name), "./server.jar").expect("Failed to rename server file");
-        fs::remove_file("./forge.jar").expect("Failed to delete forge file");
+        fs::rename(&file_name, "./server.jar").map_err(|error| DownloadError::io(&file_name, error))?;
+        fs::remove_file("./forge.jar").map_err(|error| DownloadError::io("./forge.jar", error))?;
+
+        Ok(())
     }
 }
 
-async fn get_forge_build(minecraft_version: Option<String>) -> Result<String, Box<dyn Error>> {
+/// Returns the Minecraft version Forge has builds for that `minecraft_version` refers to, or the one with the newest build if `None`.
+async fn get_forge_minecraft_version(http: &Http, minecraft_version: Option<String>) -> Result<String, DownloadError> {
+    let Some(minecraft_version) = minecraft_version else {
+        return get_latest_forge_version(http).await;
+    };
+
     let url = "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
-    let response = reqwest::get(url).await?;
-    let json: Value = response.json().await?;
-
-    let game_versions = json["promos"].as_object().ok_or("Invalid JSON format")?;
-
-    if minecraft_version.is_none() {
-        let max_version = game_versions
-            .iter()
-            .flat_map(|(_, v)| v.as_str())
-            .flat_map(|v| Version::parse(v).ok())
-            .max();
-
-        Ok(max_version.unwrap().to_string())
-    } else {
-        let version = game_versions
-            .iter()
-            .filter_map(|(version, build)| {
-                let version = version.to_string().replace("-latest", "").replace("-recommended", "");
-                if version.to_string().eq(minecraft_version.clone().unwrap().as_str()) {
-                    build.as_str().map(|v| v.to_string())
-                } else {
-                    None
-                }
-            })
-            .max()
-            .ok_or("No forge version found")?;
-
-        Ok(version)
-    }
+    let json = http.get_json(url).await?;
+
+    let game_versions = json["promos"].as_object().ok_or_else(|| DownloadError::schema(url, "missing promos"))?;
+    let versions = game_versions.keys().map(|version| version.replace("-latest", "").replace("-recommended", "")).collect::<Vec<String>>();
+
+    let forge_minecraft_version = MinecraftVersion::resolve(&minecraft_version, &versions)
+        .ok_or_else(|| DownloadError::version_not_found(&minecraft_version, closest_versions(&minecraft_version, &versions)))?;
+
+    Ok(forge_minecraft_version.to_string())
+}
+
+async fn get_forge_build(http: &Http, minecraft_version: &str) -> Result<String, DownloadError> {
+    let url = "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+    let json = http.get_json(url).await?;
+
+    let game_versions = json["promos"].as_object().ok_or_else(|| DownloadError::schema(url, "missing promos"))?;
+
+    let version = game_versions
+        .iter()
+        .filter_map(|(version, build)| {
+            let version = version.to_string().replace("-latest", "").replace("-recommended", "");
+            if version == minecraft_version {
+                build.as_str().map(|v| v.to_string())
+            } else {
+                None
+            }
+        })
+        .max_by_key(|build| build.split('.').map(|part| part.parse::<u32>().unwrap_or(0)).collect::<Vec<u32>>())
+        .ok_or_else(|| DownloadError::version_not_found(minecraft_version, vec![]))?;
+
+    Ok(version)
 }
 
-async fn get_latest_forge_version() -> Result<String, Box<dyn Error>> {
+async fn get_latest_forge_version(http: &Http) -> Result<String, DownloadError> {
     let url = "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
-    let response = reqwest::get(url).await?;
-    let json: Value = response.json().await?;
+    let json = http.get_json(url).await?;
 
-    let game_versions = json["promos"].as_object().ok_or("Invalid JSON format")?;
+    let game_versions = json["promos"].as_object().ok_or_else(|| DownloadError::schema(url, "missing promos"))?;
 
     let max_version = game_versions
         .iter()
         .flat_map(|(_, v)| v.as_str())
         .flat_map(|v| Version::parse(v).ok())
-        .max();
+        .max()
+        .ok_or_else(|| DownloadError::schema(url, "no Forge builds listed"))?;
 
-    let minecraft_version = game_versions
+    let versions = game_versions
         .iter()
         .filter_map(|(version, build)| {
             let version = version.to_string().replace("-latest", "").replace("-recommended", "");
-            if build.to_string().replace('"', "").eq(max_version.clone().unwrap().to_string().as_str()) {
+            if build.as_str() == Some(max_version.to_string().as_str()) {
                 Some(version)
             } else {
                 None
             }
         })
-        .max();
+        .collect::<Vec<String>>();
 
-    Ok(minecraft_version.unwrap().to_string())
-}
\ No newline at end of file
+    let minecraft_version = MinecraftVersion::latest(&versions)
+        .cloned()
+        .ok_or_else(|| DownloadError::schema(url, "no Minecraft version for the latest Forge build"))?;
+
+    Ok(minecraft_version)
+}
@@ -1,11 +1,17 @@
 
 use async_trait::async_trait;
-use reqwest::Client;
-use crate::downloader::{basic_server_address_from_string, download_file, Installer};
+use crate::downloader::{basic_server_address_from_string, Installer};
+use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::changelog::{get_papermc_changelog, installed, BuildChanges};
+use crate::checksum::{Checksum, ChecksumAlgorithm};
+use crate::installplan::{Artifact, ServerPlan};
+use crate::instance::Instance;
 use crate::servertype::ServerType;
+use crate::versioninfo::VersionInfo;
 use crate::servertype::ServerType::Server;
 
-pub(crate) struct Geyser {}
+pub struct Geyser {}
 
 #[async_trait]
 impl Installer for Geyser {
@@ -13,6 +19,10 @@ impl Installer for Geyser {
         "Geyser".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "geyser".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A server that support Bedrock <-> Java crossplay..".to_string()
     }
@@ -25,24 +35,55 @@ impl Installer for Geyser {
         false
     }
 
+    fn addon_directory(&self) -> Option<String> {
+        Some("extensions".to_string())
+    }
+
     fn version_required(&self) -> bool {
         false
     }
 
-    async fn get_versions(&self, client: Client) -> Vec<String> {
-        let json: serde_json::Value = client.get("https://download.geysermc.org/v2/projects/geyser/versions/latest").send().await.expect("Failed to get latest version for Geyser").json().await.expect("Failed to get latest version for Geyser");
-        let version = json["version"].as_str().unwrap().to_string();
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
+        let url = "https://download.geysermc.org/v2/projects/geyser/versions/latest";
+        let json = http.get_json(url).await?;
+
+        let version = json["version"].as_str().ok_or_else(|| DownloadError::schema(url, "missing version"))?;
 
-        vec![version]
+        Ok(vec![VersionInfo { java_major: None, ..VersionInfo::from_id(version) }])
     }
 
     async fn startup_message(&self, string: String) -> Option<std::net::SocketAddrV4> {
         basic_server_address_from_string(string).await
     }
 
-    async fn download(&self, client: reqwest::Client, _minecraft_version: Option<String>) -> Result<String, crate::downloaderror::DownloadError> {
-        download_file(&client, "https://download.geysermc.org/v2/projects/geyser/versions/latest/builds/latest/downloads/standalone", "./server.jar").await?;
+    async fn resolve(&self, http: Http, _minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let url = "https://download.geysermc.org/v2/projects/geyser/versions/latest/builds/latest";
+        let json = http.get_json(url).await?;
+
+        let version = json["version"].as_str().ok_or_else(|| DownloadError::schema(url, "missing version"))?;
+        let build = json["build"].as_u64().ok_or_else(|| DownloadError::schema(url, "missing build"))?;
+        let sha256 = json["downloads"]["standalone"]["sha256"].as_str();
+
+        Ok(ServerPlan {
+            minecraft_version: None,
+            version: Some(version.to_string()),
+            build: Some(build.to_string()),
+            artifact: Artifact {
+                url: format!("https://download.geysermc.org/v2/projects/geyser/versions/{}/builds/{}/downloads/standalone", version, build),
+                path: "./server.jar".to_string(),
+                checksum: sha256.map(|sha256| Checksum::new(ChecksumAlgorithm::Sha256, sha256)),
+            },
+            files: vec!["server.jar".to_string()],
+        })
+    }
+
+    async fn changelog(&self, http: Http, installed_server: &Instance, to: Option<String>) -> Result<Option<Vec<BuildChanges>>, DownloadError> {
+        let url = format!(
+            "https://download.geysermc.org/v2/projects/geyser/versions/{}/builds",
+            installed(&installed_server.version, "Geyser version")?
+        );
+        let builds = get_papermc_changelog(&http, &url, installed_server.build.as_deref(), to.as_deref()).await?;
 
-        Ok("".to_string())
+        Ok(Some(builds))
     }
 }
\ No newline at end of file
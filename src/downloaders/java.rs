@@ -1,38 +1,57 @@
-use reqwest::Client;
+use flate2::read::GzDecoder;
 use std::fs::File;
+use std::io;
 use std::path::Path;
+use tar::Archive;
+use crate::checksum::Checksum;
 use crate::downloader::download_file;
-
-use crate::extract;
+use crate::downloaderror::DownloadError;
+use crate::http::Http;
 use crate::os::OS;
 
-pub async fn download_java(client: &Client, java_install_path: &str, java_path: &str, url: &str, os: &OS) -> Result<(), String> {
+/// Downloads the Java runtime at `url` and extracts it into `java_install_path`.
+///
+/// Nothing is downloaded if `java_path`, the executable inside the runtime, already exists. The archive
+/// is checked against `checksum` before it is extracted. Runs alongside other downloads on `http`.
+pub async fn download_java(http: &Http, java_install_path: &str, java_path: &str, url: &str, checksum: Option<&Checksum>, os: &OS) -> Result<(), DownloadError> {
     if !Path::new(java_path).exists() {
-        if os == &OS::Windows {
-            println!("Downloading Java...");
-            download_file(client, url, "./java.zip")
-                .await
-                .expect("Failed to download Java");
+        let archive_path = if os == &OS::Windows {
+            "./java.zip"
+        } else {
+            "./java.tar.gz"
+        };
 
-            println!("Extracting Java...");
-            extract(&File::open("./java.zip").expect("Failed to unzip old Java file"), java_install_path, os);
+        let progress = http.progress();
 
-            println!("Deleting old Java file...");
-            std::fs::remove_file("./java.zip").expect("Failed to delete old Java file");
-        } else {
-            println!("Downloading Java...");
-            download_file(client, url, "./java.tar.gz")
-                .await
-                .expect("Failed to download Java");
+        progress.suspend(|| println!("Downloading Java..."));
+        download_file(http, url, archive_path, checksum).await?;
 
-            println!("Extracting Java...");
-            extract(&File::open("./java.tar.gz").expect("Failed to unzip old Java file"), java_install_path, os);
+        progress.suspend(|| println!("Extracting Java..."));
+        let archive = File::open(archive_path).map_err(|error| DownloadError::io(archive_path, error))?;
+        let (install_path, extract_os) = (java_install_path.to_string(), *os);
+        tokio::task::spawn_blocking(move || extract(&archive, &install_path, &extract_os))
+            .await
+            .map_err(io::Error::other)
+            .and_then(|result| result)
+            .map_err(|error| DownloadError::io(archive_path, error))?;
 
-            println!("Deleting old Java file...");
-            std::fs::remove_file("./java.tar.gz").expect("Failed to delete old Java file");
-        }
+        progress.suspend(|| println!("Deleting old Java file..."));
+        std::fs::remove_file(archive_path).map_err(|error| DownloadError::io(archive_path, error))?;
     } else {
-        println!("Java is ready.");
+        http.progress().suspend(|| println!("Java is ready."));
     }
     Ok(())
 }
+
+/// Extracts a Java archive into `path`. Windows runtimes are zip files, every other OS uses `.tar.gz`.
+pub fn extract(file: &File, path: &str, os: &OS) -> io::Result<()> {
+    if os == &OS::Windows {
+        let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+        archive.extract(path).map_err(io::Error::other)
+    } else {
+        let decompressed = GzDecoder::new(file);
+
+        let mut archive = Archive::new(decompressed);
+        archive.unpack(path)
+    }
+}
@@ -1,11 +1,48 @@
-pub(crate) mod fabric;
-pub(crate) mod java;
-pub(crate) mod paper;
-pub(crate) mod vanilla;
-pub(crate) mod forge;
-pub(crate) mod neoforge;
-pub(crate) mod bungeecord;
-pub(crate) mod velocity;
-pub(crate) mod waterfall;
-pub(crate) mod geyser;
-pub(crate) mod purpur;
\ No newline at end of file
+use std::ops::Deref;
+use crate::downloader::Installer;
+use crate::downloaders::bungeecord::BungeeCord;
+use crate::downloaders::fabric::Fabric;
+use crate::downloaders::forge::Forge;
+use crate::downloaders::geyser::Geyser;
+use crate::downloaders::neoforge::NeoForge;
+use crate::downloaders::paper::Paper;
+use crate::downloaders::purpur::Purpur;
+use crate::downloaders::vanilla::Vanilla;
+use crate::downloaders::velocity::Velocity;
+use crate::downloaders::waterfall::Waterfall;
+
+pub mod fabric;
+pub mod java;
+pub mod paper;
+pub mod vanilla;
+pub mod forge;
+pub mod neoforge;
+pub mod bungeecord;
+pub mod velocity;
+pub mod waterfall;
+pub mod geyser;
+pub mod purpur;
+
+/// Returns every built-in installer, servers first and proxies last, in the order they are shown in the menu.
+pub fn installers() -> Vec<Box<dyn Installer>> {
+    vec![
+        Box::new(Vanilla {}),
+        Box::new(Paper {}),
+        Box::new(Purpur {}),
+        Box::new(Fabric {}),
+        Box::new(Forge {}),
+        Box::new(NeoForge {}),
+        Box::new(Geyser {}),
+        Box::new(BungeeCord {}),
+        Box::new(Velocity {}),
+        Box::new(Waterfall {}),
+    ]
+}
+
+/// Finds an installer by its ID or name, ignoring case.
+pub fn find_installer<'a>(installers: &'a [Box<dyn Installer>], name: &str) -> Option<&'a dyn Installer> {
+    installers
+        .iter()
+        .find(|installer| installer.get_id().eq_ignore_ascii_case(name) || installer.get_name().eq_ignore_ascii_case(name))
+        .map(|installer| installer.deref())
+}
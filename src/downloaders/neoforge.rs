@@ -1,15 +1,23 @@
 use std::fs::File;
 use std::io::Read;
 use async_trait::async_trait;
-use reqwest::Client;
 use serde_json::Value;
 use tokio::fs;
 use xml2json_rs::JsonBuilder;
-use crate::downloader::Installer;
+use semver::Version;
+use crate::downloader::{closest_versions, get_published_checksum, Installer};
+use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::changelog::{installed, select_builds, BuildChanges};
+use crate::checksum::ChecksumAlgorithm;
+use crate::installplan::{Artifact, ServerPlan};
+use crate::instance::Instance;
+use crate::minecraftversion::MinecraftVersion;
 use crate::servertype::ServerType;
+use crate::versioninfo::VersionInfo;
 use crate::servertype::ServerType::Server;
 
-pub(crate) struct NeoForge {}
+pub struct NeoForge {}
 
 #[async_trait]
 impl Installer for NeoForge {
@@ -17,6 +25,10 @@ impl Installer for NeoForge {
         "NeoForge".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "neoforge".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A server that supports NeoForge mods.".to_string()
     }
@@ -29,29 +41,45 @@ impl Installer for NeoForge {
         true
     }
 
-    async fn get_versions(&self, _client: Client) -> Vec<String> {
-        let mut versions: Vec<String> = vec![];
+    fn addon_directory(&self) -> Option<String> {
+        Some("mods".to_string())
+    }
+
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
+        let mut versions: Vec<VersionInfo> = vec![];
+
+        for version in get_version_array(&http).await? {
+            let Some(neo_version) = version.as_str() else {
+                continue
+            };
 
-        for version in get_version_array().await {
-            let array = version.as_str().unwrap().split('.').collect::<Vec<&str>>();
-            let full_string = format!("1.{}", array[0].to_string() + "." + array[1]);
+            let Some(full_string) = neoforge_minecraft_version(neo_version) else {
+                continue
+            };
 
-            if !versions.contains(&full_string) {
-                versions.push(full_string);
+            let stable = !neo_version.contains("-beta");
+
+            match versions.iter_mut().find(|version| version.id == full_string) {
+                Some(version) => version.stable |= stable,
+                None => versions.push(VersionInfo {
+                    stable,
+                    ..VersionInfo::from_id(&full_string)
+                }),
             }
         }
 
-        versions
+        // Maven lists versions in the order they were published, which mixes Minecraft versions.
+        versions.sort_by_key(|version| MinecraftVersion::parse(&version.id));
+
+        Ok(versions)
     }
 
     async fn startup_message(&self, string: String) -> Option<std::net::SocketAddrV4> {
         crate::downloader::basic_server_address_from_string(string).await
     }
 
-    async fn download(&self, client: Client, minecraft_version: Option<String>) -> Result<String, crate::downloaderror::DownloadError> {
-        let neo_version = get_neoforge_version(minecraft_version).await.expect("Failed to get latest NeoForge version");
-
-        println!("Using NeoForge version {}.", neo_version);
+    async fn resolve(&self, http: Http, minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let neo_version = get_neoforge_version(&http, minecraft_version).await?;
 
         let url = format!(
             "https://maven.neoforged.net/releases/net/neoforged/neoforge/{}/neoforge-{}-installer.jar",
@@ -59,100 +87,168 @@ impl Installer for NeoForge {
             neo_version
         );
 
-        crate::downloader::download_file(&client, &url, "./neoforge.jar").await?;
-
-        Ok(neo_version)
+        let sha1 = get_published_checksum(&http, &url, ".sha1", ChecksumAlgorithm::Sha1).await?;
+
+        Ok(ServerPlan {
+            minecraft_version: neoforge_minecraft_version(&neo_version),
+            version: Some(neo_version),
+            build: None,
+            artifact: Artifact {
+                url,
+                path: "./neoforge.jar".to_string(),
+                checksum: sha1,
+            },
+            files: vec!["libraries/".to_string(), "launch.sh".to_string(), "launch.bat".to_string()],
+        })
     }
 
-    async fn build(&self, java_path: String, mut minecraft_version: Option<String>) {
+    async fn build(&self, java_path: String, plan: &ServerPlan) -> Result<(), DownloadError> {
         let mut command = std::process::Command::new(java_path.clone());
 
-        if minecraft_version.is_none() {
-            minecraft_version = Some(get_neoforge_version(None).await.expect("Failed to get latest forge version"));
-        } else {
-            minecraft_version = Some(get_neoforge_version(minecraft_version).await.expect("Failed to get latest forge version"));
-        }
+        let neo_version = plan.version.clone().unwrap_or_default();
 
         let mut process = command
             .arg("-jar")
             .arg("neoforge.jar")
             .arg("--installServer")
             .spawn()
-            .expect("Failed to build server");
+            .map_err(|error| DownloadError::io("neoforge.jar", error))?;
 
-        println!("Building server with NeoForge version {}. This will take a while...", minecraft_version.clone().unwrap_or("".to_string()));
+        println!("Building server with NeoForge version {}. This will take a while...", neo_version);
 
-        process.wait().expect("Failed to build server");
+        let status = process.wait().map_err(|error| DownloadError::io("neoforge.jar", error))?;
 
-        fs::remove_file("neoforge.jar").await.expect("Failed to remove NeoForge jar");
-        fs::rename("run.sh", "launch.sh").await.expect("Failed to rename run.sh to launch.sh");
+        if !status.success() {
+            return Err(DownloadError::InstallerFailed { installer: "NeoForge installer".to_string(), status: status.code() });
+        }
 
-        let mut content = String::new();
+        fs::remove_file("neoforge.jar").await.map_err(|error| DownloadError::io("neoforge.jar", error))?;
 
-        File::open("launch.sh").expect("Failed to open launch.sh").read_to_string(&mut content).expect("Failed to read launch.sh");
-        let new_content = content.replace("java", format!("\"{}\"", java_path.as_str()).as_str());
-        fs::write("launch.sh", new_content).await.expect("Failed to write to launch.sh");
+        for (script, launch_script) in [("run.sh", "launch.sh"), ("run.bat", "launch.bat")] {
+            fs::rename(script, launch_script).await.map_err(|error| DownloadError::io(script, error))?;
 
-        fs::rename("run.bat", "launch.bat").await.expect("Failed to rename run.sh to launch.sh");
+            let mut content = String::new();
 
-        let mut content = String::new();
+            File::open(launch_script)
+                .and_then(|mut file| file.read_to_string(&mut content))
+                .map_err(|error| DownloadError::io(launch_script, error))?;
 
-        File::open("launch.bat").expect("Failed to open launch.bat").read_to_string(&mut content).expect("Failed to read launch.bat");
-        let new_content = content.replace("java", format!("\"{}\"", java_path.as_str()).as_str());
-        fs::write("launch.bat", new_content).await.expect("Failed to write to launch.bat");
+            let new_content = content.replace("java", format!("\"{}\"", java_path.as_str()).as_str());
+            fs::write(launch_script, new_content).await.map_err(|error| DownloadError::io(launch_script, error))?;
+        }
 
-        fs::remove_file("user_jvm_args.txt").await.expect("Failed to remove user_jvm_args.txt");
+        fs::remove_file("user_jvm_args.txt").await.map_err(|error| DownloadError::io("user_jvm_args.txt", error))?;
 
         println!("Server built successfully!");
+
+        Ok(())
+    }
+
+    async fn changelog(&self, http: Http, installed_server: &Instance, to: Option<String>) -> Result<Option<Vec<BuildChanges>>, DownloadError> {
+        let neo_version = installed(&installed_server.version, "NeoForge version")?;
+
+        let to = match to {
+            Some(to) => to,
+            None => get_neoforge_version(&http, neoforge_minecraft_version(neo_version)).await?,
+        };
+
+        let url = format!(
+            "https://maven.neoforged.net/releases/net/neoforged/neoforge/{}/neoforge-{}-changelog.txt",
+            to,
+            to
+        );
+        let versions = parse_changelog(&http.get_text(&url).await?);
+
+        Ok(Some(select_builds(versions, |version| &version.build, Some(neo_version), Some(&to))))
     }
 }
 
-async fn get_version_array() -> Vec<Value> {
-    let response = reqwest::get("https://maven.neoforged.net/releases/net/neoforged/neoforge//maven-metadata.xml").await.expect("Failed to get NeoForge metadata");
-    let body = response.text().await.expect("Failed to get NeoForge metadata");
+async fn get_version_array(http: &Http) -> Result<Vec<Value>, DownloadError> {
+    let url = "https://maven.neoforged.net/releases/net/neoforged/neoforge/maven-metadata.xml";
+    let body = http.get_text(url).await?;
     let builder = JsonBuilder::default();
-    let json = builder.build_from_xml(body.as_str()).unwrap();
-
-    json.get("metadata").expect("Failed step 1 of NeoForge")
-        .get("versioning").expect("Failed step 2 of NeoForge")
-        .as_array().expect("Failed step 3 of NeoForge")
-        .first().expect("Failed step 4 of NeoForge")
-        .get("versions").expect("Failed step 5 of NeoForge")
-        .as_array().expect("Failed step 6 of NeoForge")
-        .first().expect("Failed step 7 of NeoForge")
-        .as_object().expect("Failed step 8 of NeoForge")
-        .get("version").expect("Failed step 9 of NeoForge")
-        .as_array().expect("Failed step 10 of NeoForge")
-        .to_owned()
+    let json = builder.build_from_xml(body.as_str()).map_err(|error| DownloadError::schema(url, &error.to_string()))?;
+
+    json.get("metadata")
+        .and_then(|metadata| metadata.get("versioning"))
+        .and_then(|versioning| versioning.as_array())
+        .and_then(|versioning| versioning.first())
+        .and_then(|versioning| versioning.get("versions"))
+        .and_then(|versions| versions.as_array())
+        .and_then(|versions| versions.first())
+        .and_then(|versions| versions.as_object())
+        .and_then(|versions| versions.get("version"))
+        .and_then(|version| version.as_array())
+        .map(|versions| versions.to_owned())
+        .ok_or_else(|| DownloadError::schema(url, "missing versioning.versions.version"))
 }
 
-async fn get_neoforge_version(minecraft_version: Option<String>) -> Result<String, Box<dyn std::error::Error>> {
-    let versions = get_version_array().await;
+/// Groups the entries of a NeoForge changelog by version. Each version's changelog lists every earlier version of
+/// its Minecraft version as well, one change per line like `` - `20.4.237` Fix a crash (#123)``.
+fn parse_changelog(content: &str) -> Vec<BuildChanges> {
+    let mut versions: Vec<BuildChanges> = vec![];
+
+    for line in content.lines() {
+        let Some((version, change)) = line.trim().strip_prefix("- `").and_then(|line| line.split_once('`')) else {
+            continue;
+        };
+
+        let change = change.trim().to_string();
+
+        match versions.iter_mut().find(|entry| entry.build == version) {
+            Some(entry) => entry.changes.push(change),
+            None => versions.push(BuildChanges {
+                build: version.to_string(),
+                date: None,
+                channel: None,
+                experimental: Version::parse(version).is_ok_and(|version| !version.pre.is_empty()),
+                changes: vec![change],
+            }),
+        }
+    }
 
-    if minecraft_version.is_none() {
-        let neoforge_version = versions
-            .iter()
-            .map(|version| version.as_str().unwrap().to_string())
-            .max()
-            .expect("Version not found!");
+    versions
+}
 
-        Ok(neoforge_version)
+/// Returns the Minecraft version a NeoForge version is built for, e.g. `1.20.4` for `20.4.237` and `1.21` for `21.0.0-beta`.
+fn neoforge_minecraft_version(neo_version: &str) -> Option<String> {
+    let mut parts = neo_version.split('.');
+    let minor = parts.next()?.parse::<u32>().ok()?;
+    let patch = parts.next()?.parse::<u32>().ok()?;
+
+    if patch == 0 {
+        Some(format!("1.{}", minor))
+    } else {
+        Some(format!("1.{}.{}", minor, patch))
+    }
+}
+
+async fn get_neoforge_version(http: &Http, minecraft_version: Option<String>) -> Result<String, DownloadError> {
+    let url = "https://maven.neoforged.net/releases/net/neoforged/neoforge/maven-metadata.xml";
+    let versions = get_version_array(http)
+        .await?
+        .iter()
+        .filter_map(|version| version.as_str().map(|version| version.to_string()))
+        .collect::<Vec<String>>();
+
+    let newest = |versions: Vec<&String>| versions.into_iter().max_by_key(|version| Version::parse(version).ok()).cloned();
+
+    if let Some(minecraft_version) = minecraft_version {
+        let mut minecraft_versions = versions.iter().filter_map(|version| neoforge_minecraft_version(version)).collect::<Vec<String>>();
+        minecraft_versions.sort_by_key(|version| MinecraftVersion::parse(version));
+        minecraft_versions.dedup();
+
+        let resolved = MinecraftVersion::resolve(&minecraft_version, &minecraft_versions)
+            .ok_or_else(|| DownloadError::version_not_found(&minecraft_version, closest_versions(&minecraft_version, &minecraft_versions)))?;
+
+        let latest_version = newest(versions.iter().filter(|version| neoforge_minecraft_version(version).as_ref() == Some(resolved)).collect())
+            .ok_or_else(|| DownloadError::version_not_found(&minecraft_version, vec![]))?;
+
+        Ok(latest_version)
     } else {
-        let cut_version = minecraft_version.unwrap().chars().skip(2).collect::<String>();
-
-        let latest_version = versions
-            .iter()
-            .filter_map(|version| {
-                let version = version.as_str()?;
-                if version.starts_with(&cut_version.clone()) {
-                    Some(version)
-                } else {
-                    None
-                }
-            })
-            .max()
-            .expect("Version not found!");
-
-        Ok(latest_version.to_string())
+        let neoforge_version = newest(versions.iter().collect())
+            .ok_or_else(|| DownloadError::schema(url, "no versions listed"))?;
+
+        Ok(neoforge_version)
     }
-}
\ No newline at end of file
+}
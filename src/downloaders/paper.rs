@@ -1,14 +1,18 @@
-
-use std::error::Error;
 use std::net::SocketAddrV4;
 use async_trait::async_trait;
-use reqwest::Client;
-use crate::downloader::{basic_server_address_from_string, download_file, Installer};
+use crate::downloader::{basic_server_address_from_string, closest_versions, Installer};
 use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::changelog::{get_papermc_changelog, installed, BuildChanges};
+use crate::checksum::{Checksum, ChecksumAlgorithm};
+use crate::installplan::{Artifact, ServerPlan};
+use crate::instance::Instance;
+use crate::minecraftversion::MinecraftVersion;
 use crate::servertype::ServerType;
+use crate::versioninfo::VersionInfo;
 use crate::servertype::ServerType::Server;
 
-pub(crate) struct Paper {}
+pub struct Paper {}
 
 #[async_trait]
 impl Installer for Paper {
@@ -16,6 +20,10 @@ impl Installer for Paper {
         "Paper".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "paper".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A server that supports Paper plugins.".to_string()
     }
@@ -28,32 +36,23 @@ impl Installer for Paper {
         false
     }
 
-    async fn get_versions(&self, client: Client) -> Vec<String> {
-        let json: serde_json::Value = client.get("https://papermc.io/api/v2/projects/paper").send().await.expect("Failed to get latest version for Paper").json().await.expect("Failed to get latest version for Paper");
-
-        let versions = json["versions"].as_array().unwrap();
-        let mut version_strings = Vec::new();
+    fn addon_directory(&self) -> Option<String> {
+        Some("plugins".to_string())
+    }
 
-        for version in versions {
-            let version_string = version.as_str().unwrap().to_string();
-            version_strings.push(version_string);
-        }
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
+        let versions = get_paper_versions(&http).await?;
 
-        version_strings
+        Ok(versions.iter().map(|version| VersionInfo::from_id(version)).collect())
     }
 
     async fn startup_message(&self, string: String) -> Option<SocketAddrV4> {
         basic_server_address_from_string(string).await
     }
 
-    async fn download(&self, client: Client, minecraft_version: Option<String>) -> Result<String, DownloadError> {
-        let paper_version = get_latest_paper_version(minecraft_version).await.expect("Failed to get latest paper version");
-        let latest_build = get_latest_build(&paper_version).await.expect("Failed to get latest paper build");
-
-        println!(
-            "Using Paper version {} with build {}.",
-            paper_version, latest_build
-        );
+    async fn resolve(&self, http: Http, minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let paper_version = get_latest_paper_version(&http, minecraft_version).await?;
+        let (latest_build, sha256) = get_latest_build(&http, &paper_version).await?;
 
         let url = format!(
             "https://api.papermc.io/v2/projects/paper/versions/{}/builds/{}/downloads/paper-{}-{}.jar",
@@ -62,67 +61,79 @@ impl Installer for Paper {
             paper_version, latest_build
         );
 
-        download_file(&client, &url, "./server.jar").await?;
+        Ok(ServerPlan {
+            minecraft_version: Some(paper_version),
+            version: None,
+            build: Some(latest_build),
+            artifact: Artifact {
+                url,
+                path: "./server.jar".to_string(),
+                checksum: sha256.map(|sha256| Checksum::new(ChecksumAlgorithm::Sha256, &sha256)),
+            },
+            files: vec!["server.jar".to_string()],
+        })
+    }
+
+    async fn changelog(&self, http: Http, installed_server: &Instance, to: Option<String>) -> Result<Option<Vec<BuildChanges>>, DownloadError> {
+        let url = format!(
+            "https://api.papermc.io/v2/projects/paper/versions/{}/builds",
+            installed(&installed_server.minecraft_version, "Minecraft version")?
+        );
+        let builds = get_papermc_changelog(&http, &url, installed_server.build.as_deref(), to.as_deref()).await?;
 
-        Ok(paper_version)
+        Ok(Some(builds))
     }
 }
 
-async fn get_latest_paper_version(minecraft_version: Option<String>) -> Result<String, Box<dyn Error>> {
-    let url = "https://papermc.io/api/v2/projects/paper";
-    let response = reqwest::get(url).await?;
-    let json: serde_json::Value = response.json().await?;
-    let versions = json["versions"].as_array().ok_or("JSON is invalid!")?;
+async fn get_paper_versions(http: &Http) -> Result<Vec<String>, DownloadError> {
+    let url = "https://api.papermc.io/v2/projects/paper";
+    let json = http.get_json(url).await?;
 
-    if minecraft_version.is_none() {
-        let paper_version = versions
-            .last()
-            .and_then(|v| v.as_str())
-            .ok_or("Version not found!")?;
+    let versions = json["versions"]
+        .as_array()
+        .ok_or_else(|| DownloadError::schema(url, "missing versions array"))?
+        .iter()
+        .filter_map(|version| version.as_str().map(|version| version.to_string()))
+        .collect::<Vec<String>>();
+
+    Ok(versions)
+}
+
+async fn get_latest_paper_version(http: &Http, minecraft_version: Option<String>) -> Result<String, DownloadError> {
+    let versions = get_paper_versions(http).await?;
+
+    if let Some(minecraft_version) = minecraft_version {
+        let paper_version = MinecraftVersion::resolve(&minecraft_version, &versions)
+            .ok_or_else(|| DownloadError::version_not_found(&minecraft_version, closest_versions(&minecraft_version, &versions)))?;
 
         Ok(paper_version.to_string())
     } else {
-        let minecraft_version = minecraft_version.unwrap();
         let paper_version = versions
-            .iter()
-            .filter_map(|version| {
-                let version = version.as_str()?;
-                if version.starts_with(&minecraft_version) {
-                    Some(version)
-                } else {
-                    None
-                }
-            })
-            .max()
-            .ok_or("Version not found!")?;
+            .last()
+            .ok_or_else(|| DownloadError::schema("https://api.papermc.io/v2/projects/paper", "no versions listed"))?;
 
         Ok(paper_version.to_string())
     }
 }
 
-async fn get_latest_build(paper_version: &str) -> Result<String, Box<dyn Error>> {
+/// Returns the newest build on the default channel and the SHA-256 of its jar.
+async fn get_latest_build(http: &Http, paper_version: &str) -> Result<(String, Option<String>), DownloadError> {
     let url = format!(
         "https://api.papermc.io/v2/projects/paper/versions/{}/builds",
         paper_version
     );
-    let response = reqwest::get(&url).await?;
-    let json: serde_json::Value = response.json().await?;
+    let json = http.get_json(&url).await?;
 
     let build = json["builds"]
         .as_array()
-        .ok_or("JSON is invalid")?
+        .ok_or_else(|| DownloadError::schema(&url, "missing builds array"))?
         .iter()
-        .filter_map(|build| {
-            let channel = build["channel"].as_str()?;
-            let build_number = build["build"].as_u64()?;
-            if channel == "default" {
-                Some(build_number)
-            } else {
-                None
-            }
-        })
-        .max()
-        .ok_or("No builds found")?;
+        .filter(|build| build["channel"].as_str() == Some("default"))
+        .max_by_key(|build| build["build"].as_u64())
+        .ok_or_else(|| DownloadError::version_not_found(paper_version, vec![]))?;
+
+    let build_number = build["build"].as_u64().ok_or_else(|| DownloadError::schema(&url, "build without a number"))?;
+    let sha256 = build["downloads"]["application"]["sha256"].as_str().map(|sha256| sha256.to_string());
 
-    Ok(build.to_string())
+    Ok((build_number.to_string(), sha256))
 }
@@ -1,15 +1,22 @@
-
-use std::error::Error;
 use std::net::SocketAddrV4;
-use std::ops::Deref;
 use async_trait::async_trait;
-use reqwest::Client;
-use crate::downloader::{basic_server_address_from_string, download_file, Installer};
+use futures_util::future::join_all;
+use crate::downloader::{basic_server_address_from_string, closest_versions, Installer};
 use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::changelog::{date_from_timestamp, first_line, installed, select_builds, BuildChanges};
+use crate::checksum::{Checksum, ChecksumAlgorithm};
+use crate::installplan::{Artifact, ServerPlan};
+use crate::instance::Instance;
+use crate::minecraftversion::MinecraftVersion;
 use crate::servertype::ServerType;
+use crate::versioninfo::VersionInfo;
 use crate::servertype::ServerType::Server;
 
-pub(crate) struct Purpur {}
+/// How many builds the changelog fetches at the same time.
+const CHANGELOG_REQUESTS: usize = 8;
+
+pub struct Purpur {}
 
 #[async_trait]
 impl Installer for Purpur {
@@ -17,6 +24,10 @@ impl Installer for Purpur {
         "Purpur".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "purpur".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A fast, PaperMC fork.".to_string()
     }
@@ -29,32 +40,25 @@ impl Installer for Purpur {
         false
     }
 
-    async fn get_versions(&self, client: Client) -> Vec<String> {
-        let json: serde_json::Value = client.get("https://api.purpurmc.org/v2/purpur/").send().await.expect("Failed to get latest version for Purpur").json().await.expect("Failed to get latest version for Purpur");
-
-        let versions = json["versions"].as_array().unwrap();
-        let mut version_strings = Vec::new();
+    fn addon_directory(&self) -> Option<String> {
+        Some("plugins".to_string())
+    }
 
-        for version in versions {
-            let version_string = version.as_str().unwrap().to_string();
-            version_strings.push(version_string);
-        }
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
+        let versions = get_purpur_versions(&http).await?;
 
-        version_strings
+        Ok(versions.iter().map(|version| VersionInfo::from_id(version)).collect())
     }
 
     async fn startup_message(&self, string: String) -> Option<SocketAddrV4> {
         basic_server_address_from_string(string).await
     }
 
-    async fn download(&self, client: Client, minecraft_version: Option<String>) -> Result<String, DownloadError> {
-        let purpur_version = get_latest_purpur_version(minecraft_version).await.expect("Failed to get latest Purpur version");
-        let latest_build = get_latest_build(&purpur_version).await.expect("Failed to get latest Purpur build");
+    async fn resolve(&self, http: Http, minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let purpur_version = get_latest_purpur_version(&http, minecraft_version).await?;
+        let latest_build = get_latest_build(&http, &purpur_version).await?;
 
-        println!(
-            "Using Purpur version {} with build {}.",
-            purpur_version, latest_build
-        );
+        let md5 = get_build_md5(&http, &purpur_version, &latest_build).await?;
 
         let url = format!(
             "https://api.purpurmc.org/v2/purpur/{}/{}/download",
@@ -62,51 +66,128 @@ impl Installer for Purpur {
             latest_build,
         );
 
-        download_file(&client, &url, "./server.jar").await?;
+        Ok(ServerPlan {
+            minecraft_version: Some(purpur_version),
+            version: None,
+            build: Some(latest_build),
+            artifact: Artifact {
+                url,
+                path: "./server.jar".to_string(),
+                checksum: md5.map(|md5| Checksum::new(ChecksumAlgorithm::Md5, &md5)),
+            },
+            files: vec!["server.jar".to_string()],
+        })
+    }
+
+    async fn changelog(&self, http: Http, installed_server: &Instance, to: Option<String>) -> Result<Option<Vec<BuildChanges>>, DownloadError> {
+        let purpur_version = installed(&installed_server.minecraft_version, "Minecraft version")?;
+        let builds = select_builds(get_builds(&http, purpur_version).await?, |build| build, installed_server.build.as_deref(), to.as_deref());
 
-        Ok(purpur_version)
+        let mut changelog = vec![];
+
+        for chunk in builds.chunks(CHANGELOG_REQUESTS) {
+            let changes = join_all(chunk.iter().map(|build| get_build_changes(&http, purpur_version, build))).await;
+
+            changelog.extend(changes.into_iter().collect::<Result<Vec<BuildChanges>, DownloadError>>()?);
+        }
+
+        Ok(Some(changelog))
     }
 }
 
-async fn get_latest_purpur_version(minecraft_version: Option<String>) -> Result<String, Box<dyn Error>> {
+async fn get_purpur_versions(http: &Http) -> Result<Vec<String>, DownloadError> {
     let url = "https://api.purpurmc.org/v2/purpur/";
-    let response = reqwest::get(url).await?;
-    let json: serde_json::Value = response.json().await?;
-    let versions = json["versions"].as_array().ok_or("JSON is invalid!")?;
+    let json = http.get_json(url).await?;
 
-    if minecraft_version.is_none() {
-        let purpur_version = versions
-            .last()
-            .and_then(|v| v.as_str())
-            .ok_or("Version not found!")?;
+    let versions = json["versions"]
+        .as_array()
+        .ok_or_else(|| DownloadError::schema(url, "missing versions array"))?
+        .iter()
+        .filter_map(|version| version.as_str().map(|version| version.to_string()))
+        .collect::<Vec<String>>();
+
+    Ok(versions)
+}
+
+async fn get_latest_purpur_version(http: &Http, minecraft_version: Option<String>) -> Result<String, DownloadError> {
+    let versions = get_purpur_versions(http).await?;
+
+    if let Some(minecraft_version) = minecraft_version {
+        let purpur_version = MinecraftVersion::resolve(&minecraft_version, &versions)
+            .ok_or_else(|| DownloadError::version_not_found(&minecraft_version, closest_versions(&minecraft_version, &versions)))?;
 
         Ok(purpur_version.to_string())
     } else {
         let purpur_version = versions
-            .iter()
-            .filter_map(|v| v.as_str())
-            .find(|v| v.contains(minecraft_version.as_ref().unwrap().deref()))
-            .ok_or("Version not found!")?;
+            .last()
+            .ok_or_else(|| DownloadError::schema("https://api.purpurmc.org/v2/purpur/", "no versions listed"))?;
 
         Ok(purpur_version.to_string())
     }
 }
 
-async fn get_latest_build(purpur_version: &str) -> Result<String, Box<dyn Error>> {
+async fn get_latest_build(http: &Http, purpur_version: &str) -> Result<String, DownloadError> {
     let url = format!(
         "https://api.purpurmc.org/v2/purpur/{}",
         purpur_version
     );
-    let response = reqwest::get(&url).await?;
-    let json: serde_json::Value = response.json().await?;
+    let json = http.get_json(&url).await?;
 
     let build = json["builds"]
-        .as_object()
-        .expect("JSON is invalid")
         .get("latest")
-        .expect("No builds found")
-        .as_str()
-        .expect("Failed to get latest build as string");
+        .and_then(|latest| latest.as_str())
+        .ok_or_else(|| DownloadError::schema(&url, "missing latest build"))?;
 
     Ok(build.to_string())
 }
+
+async fn get_builds(http: &Http, purpur_version: &str) -> Result<Vec<String>, DownloadError> {
+    let url = format!(
+        "https://api.purpurmc.org/v2/purpur/{}",
+        purpur_version
+    );
+    let json = http.get_json(&url).await?;
+
+    let builds = json["builds"]["all"]
+        .as_array()
+        .ok_or_else(|| DownloadError::schema(&url, "missing builds"))?
+        .iter()
+        .filter_map(|build| build.as_str().map(|build| build.to_string()))
+        .collect::<Vec<String>>();
+
+    Ok(builds)
+}
+
+/// Reads the commits that went into `build`. Purpur has no channels, so no build is marked experimental.
+async fn get_build_changes(http: &Http, purpur_version: &str, build: &str) -> Result<BuildChanges, DownloadError> {
+    let url = format!(
+        "https://api.purpurmc.org/v2/purpur/{}/{}",
+        purpur_version,
+        build
+    );
+    let json = http.get_json(&url).await?;
+
+    let changes = json["commits"]
+        .as_array()
+        .map(|commits| commits.iter().filter_map(|commit| first_line(commit["description"].as_str()?)).collect())
+        .unwrap_or_default();
+
+    Ok(BuildChanges {
+        build: build.to_string(),
+        date: json["timestamp"].as_u64().map(|timestamp| date_from_timestamp(timestamp / 1000)),
+        channel: None,
+        experimental: false,
+        changes,
+    })
+}
+
+async fn get_build_md5(http: &Http, purpur_version: &str, build: &str) -> Result<Option<String>, DownloadError> {
+    let url = format!(
+        "https://api.purpurmc.org/v2/purpur/{}/{}",
+        purpur_version,
+        build
+    );
+    let json = http.get_json(&url).await?;
+
+    Ok(json["md5"].as_str().map(|md5| md5.to_string()))
+}
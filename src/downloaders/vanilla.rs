@@ -1,12 +1,17 @@
 use std::net::SocketAddrV4;
 use async_trait::async_trait;
-use reqwest::Client;
-use crate::downloader::{basic_server_address_from_string, download_file, Installer, get_latest_vanilla_version};
+use crate::downloader::{basic_server_address_from_string, closest_versions, get_latest_vanilla_version, Installer};
 use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::config::default_java_version;
+use crate::checksum::{Checksum, ChecksumAlgorithm};
+use crate::installplan::{Artifact, ServerPlan};
+use crate::minecraftversion::MinecraftVersion;
 use crate::servertype::ServerType;
+use crate::versioninfo::{VersionInfo, VersionKind};
 use crate::servertype::ServerType::Server;
 
-pub(crate) struct Vanilla {}
+pub struct Vanilla {}
 
 #[async_trait]
 impl Installer for Vanilla {
@@ -14,6 +19,10 @@ impl Installer for Vanilla {
         "Vanilla".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "vanilla".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A basic Vanilla server.".to_string()
     }
@@ -26,49 +35,89 @@ impl Installer for Vanilla {
         false
     }
 
-    async fn get_versions(&self, _client: Client) -> Vec<String> {
-        vec!["All Versions".to_string()] // Literally all versions. This is a Vanilla server, after all.
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
+        let manifest_url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
+        let manifest_json = http.get_json(manifest_url).await?;
+
+        let versions = manifest_json
+            .get("versions")
+            .and_then(|versions| versions.as_array())
+            .ok_or_else(|| DownloadError::schema(manifest_url, "missing versions array"))?
+            .iter()
+            .rev()
+            .filter_map(|version| {
+                let id = version["id"].as_str()?;
+                let version_type = version["type"].as_str()?;
+
+                let kind = match version_type {
+                    "release" => VersionKind::Release,
+                    "old_beta" | "old_alpha" => VersionKind::Experimental,
+                    _ => match VersionKind::from_id(id) {
+                        VersionKind::Release => VersionKind::Snapshot,
+                        kind => kind,
+                    },
+                };
+
+                Some(VersionInfo {
+                    id: id.to_string(),
+                    kind,
+                    release_time: version["releaseTime"].as_str().map(|time| time.to_string()),
+                    stable: version_type == "release",
+                    java_major: Some(default_java_version(&MinecraftVersion::parse(id))),
+                })
+            })
+            .collect::<Vec<VersionInfo>>();
+
+        Ok(versions)
     }
 
     async fn startup_message(&self, string: String) -> Option<SocketAddrV4> {
         basic_server_address_from_string(string).await
     }
 
-    async fn download(&self, client: Client, minecraft_version: Option<String>) -> Result<String, DownloadError> {
-        let mut minecraft_version = minecraft_version;
-
-        println!("Downloading Vanilla server...");
+    async fn resolve(&self, http: Http, minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let minecraft_version = match minecraft_version {
+            Some(minecraft_version) => minecraft_version,
+            None => get_latest_vanilla_version(&http).await?,
+        };
 
         let manifest_url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
-        let manifest_body = reqwest::get(manifest_url).await?.text().await?;
-        let manifest_json: serde_json::Value = serde_json::from_str(&manifest_body).expect("Failed to parse manifest JSON");
-
-        if minecraft_version.is_none() {
-            minecraft_version = Some(get_latest_vanilla_version().await?);
-        }
-
-        println!("Using version {}", &minecraft_version.as_ref().unwrap());
+        let manifest_json = http.get_json(manifest_url).await?;
 
-        let version_url = manifest_json
+        let versions = manifest_json
             .get("versions")
-            .expect("Failed to get versions")
-            .as_array()
-            .expect("Failed to get versions as array")
-            .iter()
-            .find(|version| version["id"].as_str().expect("Failed to get ID") == minecraft_version.clone().unwrap())
-            .and_then(|version| version["url"].as_str());
-
-        let version_body = reqwest::get(version_url.expect("Version not found!")).await?.text().await?;
-        let server_url = serde_json::from_str::<serde_json::Value>(&version_body)
-            .ok()
-            .and_then(|json| {
-                let downloads = json.get("downloads")?.clone();
-                let server = downloads.get("server")?.clone();
-                server.get("url")?.as_str().map(|url| url.to_string())
-            }).expect("Failed to get server URL");
+            .and_then(|versions| versions.as_array())
+            .ok_or_else(|| DownloadError::schema(manifest_url, "missing versions array"))?;
 
-        download_file(&client, &server_url.to_string(), "./server.jar").await?;
+        let ids = versions.iter().rev().filter_map(|version| version["id"].as_str().map(|id| id.to_string())).collect::<Vec<String>>();
+        let minecraft_version = MinecraftVersion::resolve(&minecraft_version, &ids)
+            .cloned()
+            .ok_or_else(|| DownloadError::version_not_found(&minecraft_version, closest_versions(&minecraft_version, &ids)))?;
 
-        Ok(minecraft_version.unwrap().to_string())
+        let version_url = versions
+            .iter()
+            .find(|version| version["id"].as_str() == Some(minecraft_version.as_str()))
+            .and_then(|version| version["url"].as_str())
+            .ok_or_else(|| DownloadError::schema(manifest_url, "version without a URL"))?;
+
+        let version_json = http.get_json(version_url).await?;
+        let server = version_json
+            .get("downloads")
+            .and_then(|downloads| downloads.get("server"))
+            .ok_or_else(|| DownloadError::version_not_found(&minecraft_version, vec![]))?;
+        let server_url = server["url"].as_str().ok_or_else(|| DownloadError::schema(version_url, "server download without a URL"))?;
+        let sha1 = server["sha1"].as_str();
+
+        Ok(ServerPlan {
+            minecraft_version: Some(minecraft_version),
+            version: None,
+            build: None,
+            artifact: Artifact {
+                url: server_url.to_string(),
+                path: "./server.jar".to_string(),
+                checksum: sha1.map(|sha1| Checksum::new(ChecksumAlgorithm::Sha1, sha1)),
+            },
+            files: vec!["server.jar".to_string()],
+        })
     }
 }
@@ -1,14 +1,17 @@
-
-use std::error::Error;
 use std::net::SocketAddrV4;
 use async_trait::async_trait;
-use reqwest::Client;
-use crate::downloader::{basic_proxy_address_from_string, download_file, Installer};
+use crate::downloader::{basic_proxy_address_from_string, Installer};
 use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::changelog::{get_papermc_changelog, installed, BuildChanges};
+use crate::checksum::{Checksum, ChecksumAlgorithm};
+use crate::installplan::{Artifact, ServerPlan};
+use crate::instance::Instance;
 use crate::servertype::ServerType;
+use crate::versioninfo::{VersionInfo, VersionKind};
 use crate::servertype::ServerType::Proxy;
 
-pub(crate) struct Velocity {}
+pub struct Velocity {}
 
 #[async_trait]
 impl Installer for Velocity {
@@ -16,6 +19,10 @@ impl Installer for Velocity {
         "Velocity".to_string()
     }
 
+    fn get_id(&self) -> String {
+        "velocity".to_string()
+    }
+
     fn get_description(&self) -> String {
         "A proxy that supports Velocity plugins.".to_string()
     }
@@ -28,30 +35,33 @@ impl Installer for Velocity {
         false
     }
 
+    fn addon_directory(&self) -> Option<String> {
+        Some("plugins".to_string())
+    }
+
     fn version_required(&self) -> bool {
         false
     }
 
-    async fn get_versions(&self, client: Client) -> Vec<String> {
-        let json: serde_json::Value = client.get("https://papermc.io/api/v2/projects/velocity").send().await.expect("Failed to get latest version for Velocity").json().await.expect("Failed to get latest version for Velocity");
-
-        let versions = json["versions"].as_array().unwrap().last();
+    async fn get_versions(&self, http: Http) -> Result<Vec<VersionInfo>, DownloadError> {
+        let version = get_latest_velocity_version(&http).await?;
+        let snapshot = version.ends_with("-SNAPSHOT");
 
-        vec![versions.unwrap().as_str().unwrap().to_string()]
+        Ok(vec![VersionInfo {
+            kind: if snapshot { VersionKind::Snapshot } else { VersionKind::Release },
+            stable: !snapshot,
+            java_major: None,
+            ..VersionInfo::from_id(&version)
+        }])
     }
 
     async fn startup_message(&self, string: String) -> Option<SocketAddrV4> {
         basic_proxy_address_from_string(string).await
     }
 
-    async fn download(&self, client: Client, _minecraft_version: Option<String>) -> Result<String, DownloadError> {
-        let velocity_version = get_latest_velocity_version().await.expect("Failed to get latest velocity version");
-        let latest_build = get_latest_build(&velocity_version).await.expect("Failed to get latest velocity build");
-
-        println!(
-            "Using Velocity version {} with build {}.",
-            velocity_version, latest_build
-        );
+    async fn resolve(&self, http: Http, _minecraft_version: Option<String>) -> Result<ServerPlan, DownloadError> {
+        let velocity_version = get_latest_velocity_version(&http).await?;
+        let (latest_build, sha256) = get_latest_build(&http, &velocity_version).await?;
 
         let url = format!(
             "https://api.papermc.io/v2/projects/velocity/versions/{}/builds/{}/downloads/velocity-{}-{}.jar",
@@ -60,42 +70,60 @@ impl Installer for Velocity {
             velocity_version, latest_build
         );
 
-        download_file(&client, &url, "./server.jar").await?;
+        Ok(ServerPlan {
+            minecraft_version: None,
+            version: Some(velocity_version),
+            build: Some(latest_build),
+            artifact: Artifact {
+                url,
+                path: "./server.jar".to_string(),
+                checksum: sha256.map(|sha256| Checksum::new(ChecksumAlgorithm::Sha256, &sha256)),
+            },
+            files: vec!["server.jar".to_string()],
+        })
+    }
 
-        Ok(velocity_version)
+    async fn changelog(&self, http: Http, installed_server: &Instance, to: Option<String>) -> Result<Option<Vec<BuildChanges>>, DownloadError> {
+        let url = format!(
+            "https://api.papermc.io/v2/projects/velocity/versions/{}/builds",
+            installed(&installed_server.version, "Velocity version")?
+        );
+        let builds = get_papermc_changelog(&http, &url, installed_server.build.as_deref(), to.as_deref()).await?;
+
+        Ok(Some(builds))
     }
 }
 
-async fn get_latest_velocity_version() -> Result<String, Box<dyn Error>> {
-    let url = "https://papermc.io/api/v2/projects/velocity";
-    let response = reqwest::get(url).await?;
-    let json: serde_json::Value = response.json().await?;
-    let versions = json["versions"].as_array().ok_or("JSON is invalid!")?;
+async fn get_latest_velocity_version(http: &Http) -> Result<String, DownloadError> {
+    let url = "https://api.papermc.io/v2/projects/velocity";
+    let json = http.get_json(url).await?;
+    let versions = json["versions"].as_array().ok_or_else(|| DownloadError::schema(url, "missing versions array"))?;
 
     let velocity_version = versions
         .last()
         .and_then(|v| v.as_str())
-        .ok_or("Version not found!")?;
+        .ok_or_else(|| DownloadError::schema(url, "no versions listed"))?;
 
     Ok(velocity_version.to_string())
 }
 
-async fn get_latest_build(velocity_version: &str) -> Result<String, Box<dyn Error>> {
+/// Returns the newest build and the SHA-256 of its jar.
+async fn get_latest_build(http: &Http, velocity_version: &str) -> Result<(String, Option<String>), DownloadError> {
     let url = format!(
         "https://api.papermc.io/v2/projects/velocity/versions/{}/builds",
         velocity_version
     );
-    let response = reqwest::get(&url).await?;
-    let json: serde_json::Value = response.json().await?;
+    let json = http.get_json(&url).await?;
 
     let build = json["builds"]
         .as_array()
-        .ok_or("JSON is invalid")?
+        .ok_or_else(|| DownloadError::schema(&url, "missing builds array"))?
         .iter()
-        .max_by_key(|v| v.get("build").unwrap().as_u64().unwrap())
-        .unwrap()
-        .get("build")
-        .ok_or("No builds found")?;
+        .max_by_key(|build| build["build"].as_u64())
+        .ok_or_else(|| DownloadError::version_not_found(velocity_version, vec![]))?;
+
+    let build_number = build["build"].as_u64().ok_or_else(|| DownloadError::schema(&url, "build without a number"))?;
+    let sha256 = build["downloads"]["application"]["sha256"].as_str().map(|sha256| sha256.to_string());
 
-    Ok(build.to_string())
+    Ok((build_number.to_string(), sha256))
 }
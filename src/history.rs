@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::backup::{copy_entry, remove_entry, BACKUP_DIRECTORY};
+use crate::downloaderror::DownloadError;
+use crate::http::now;
+use crate::instance::{Instance, INSTANCE_FILE};
+use crate::lockfile::{Lockfile, LOCK_FILE};
+use crate::upgrade::describe;
+
+/// The file in each snapshot directory describing it.
+const SNAPSHOT_FILE: &str = "msi-snapshot.toml";
+
+/// A previous install, saved by [`save_snapshot`] into `backups/history` so it can be restored by [`restore_snapshot`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+    /// What was installed.
+    pub instance: Instance,
+    /// The files that were saved, relative to the server directory. Directories end with `/`.
+    pub files: Vec<String>,
+    /// The backup of the whole server directory taken at the same time, if any.
+    pub backup: Option<String>,
+    /// When the snapshot was taken, in seconds since the Unix epoch.
+    pub created_at: u64,
+    #[serde(skip)]
+    path: String,
+}
+
+impl Snapshot {
+    /// Describes the saved install, e.g. `paper 1.20.4 build 499`.
+    pub fn description(&self) -> String {
+        format!("{} {}", self.instance.distribution, describe(&self.instance.minecraft_version, &self.instance.version, &self.instance.build))
+    }
+
+    /// Deletes the snapshot and, if `with_backup`, the backup taken with it.
+    pub(crate) fn delete(&self, with_backup: bool) -> Result<(), DownloadError> {
+        fs::remove_dir_all(&self.path).map_err(|error| DownloadError::io(&self.path, error))?;
+
+        match &self.backup {
+            Some(backup) if with_backup && Path::new(backup).exists() => fs::remove_dir_all(backup).map_err(|error| DownloadError::io(backup, error)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn history_directory() -> String {
+    format!("{}/history", BACKUP_DIRECTORY)
+}
+
+/// Lists the files of the install in the current directory: the server's own files from [`LOCK_FILE`], launch
+/// scripts, JVM arguments and our records. Only files that exist are returned, without a leading `./`.
+fn install_files() -> Vec<String> {
+    let mut files = match Lockfile::load() {
+        Ok(lockfile) => [lockfile.server.files, vec![lockfile.server.artifact.path]].concat(),
+        Err(_) => vec!["server.jar".to_string()],
+    };
+
+    for file in ["launch.sh", "launch.bat", "user_jvm_args.txt", INSTANCE_FILE, LOCK_FILE] {
+        files.push(file.to_string());
+    }
+
+    let mut existing = Vec::new();
+
+    for file in files {
+        let file = file.trim_start_matches("./").to_string();
+
+        if Path::new(&file).exists() && !existing.contains(&file) {
+            existing.push(file);
+        }
+    }
+
+    existing
+}
+
+/// Saves the install in the current directory, described by `instance`, as the newest snapshot.
+///
+/// `backup` is the backup of the server directory taken before replacing the install, if any. With `keep` at 0,
+/// nothing is saved. Older snapshots are not removed until [`prune_snapshots`] is called, so a snapshot can still be
+/// deleted if the install it was taken for fails without losing any of them.
+pub fn save_snapshot(instance: &Instance, backup: Option<String>, keep: usize) -> Result<Option<Snapshot>, DownloadError> {
+    if keep == 0 {
+        return Ok(None);
+    }
+
+    let name = format!("{}/{}-{}", history_directory(), now(), instance.distribution);
+    let mut path = name.clone();
+    let mut count = 1;
+
+    // Snapshots taken within the same second get a counter instead of replacing each other.
+    while Path::new(&path).exists() {
+        count += 1;
+        path = format!("{}-{}", name, count);
+    }
+
+    let files = install_files();
+
+    fs::create_dir_all(&path).map_err(|error| DownloadError::io(&path, error))?;
+
+    for file in &files {
+        copy_entry(Path::new(file), &Path::new(&path).join(file))?;
+    }
+
+    let snapshot = Snapshot { instance: instance.clone(), files, backup, created_at: now(), path };
+    let snapshot_file = format!("{}/{}", snapshot.path, SNAPSHOT_FILE);
+    let content = toml::to_string(&snapshot).map_err(|error| DownloadError::invalid_file(&snapshot_file, &error.to_string()))?;
+
+    fs::write(&snapshot_file, content).map_err(|error| DownloadError::io(&snapshot_file, error))?;
+
+    Ok(Some(snapshot))
+}
+
+/// Deletes all but the newest `keep` snapshots, along with the backups taken with them.
+pub fn prune_snapshots(keep: usize) -> Result<(), DownloadError> {
+    for old in snapshots().iter().skip(keep) {
+        old.delete(true)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the saved snapshots in the current directory, newest first.
+pub fn snapshots() -> Vec<Snapshot> {
+    let Ok(entries) = fs::read_dir(history_directory()) else {
+        return vec![];
+    };
+
+    let mut snapshots = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path().to_string_lossy().to_string();
+            let content = fs::read_to_string(format!("{}/{}", path, SNAPSHOT_FILE)).ok()?;
+            let snapshot = toml::from_str::<Snapshot>(&content).ok()?;
+
+            Some(Snapshot { path, ..snapshot })
+        })
+        .collect::<Vec<Snapshot>>();
+
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.path.cmp(&a.path)));
+    snapshots
+}
+
+/// Replaces the install in the current directory with `snapshot`, which is then removed from the history.
+///
+/// With `with_backup`, everything in the backup taken with the snapshot, such as worlds and configs, is restored as
+/// well. Files created since then are left alone. The backup itself is kept.
+pub fn restore_snapshot(snapshot: &Snapshot, with_backup: bool) -> Result<(), DownloadError> {
+    for file in install_files() {
+        remove_entry(&file)?;
+    }
+
+    if let Some(backup) = snapshot.backup.as_ref().filter(|_| with_backup) {
+        for entry in fs::read_dir(backup).map_err(|error| DownloadError::io(backup, error))? {
+            let entry = entry.map_err(|error| DownloadError::io(backup, error))?;
+            let target = entry.file_name().to_string_lossy().to_string();
+
+            remove_entry(&target)?;
+            copy_entry(&entry.path(), Path::new(&target))?;
+        }
+    }
+
+    for file in &snapshot.files {
+        let target = file.trim_end_matches('/');
+
+        remove_entry(target)?;
+        copy_entry(&Path::new(&snapshot.path).join(target), Path::new(target))?;
+    }
+
+    snapshot.delete(false)
+}
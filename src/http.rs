@@ -0,0 +1,332 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use indicatif::MultiProgress;
+use reqwest::{Client, StatusCode};
+use reqwest::header::{ACCEPT, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LINK};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::checksum::{file_checksum, Checksum, ChecksumAlgorithm};
+use crate::downloaderror::DownloadError;
+
+/// How long metadata from each source is used before it is revalidated, matched against the URL.
+/// Content-addressed and per-build files never change, so they are kept much longer.
+const METADATA_TTLS: &[(&str, Duration)] = &[
+    ("piston-meta.mojang.com/v1/packages/", Duration::from_secs(30 * 24 * 60 * 60)),
+    (".sha1", Duration::from_secs(30 * 24 * 60 * 60)),
+    (".sha256.txt", Duration::from_secs(30 * 24 * 60 * 60)),
+    ("version_manifest", Duration::from_secs(60 * 60)),
+    ("promotions_slim.json", Duration::from_secs(60 * 60)),
+    ("maven-metadata.xml", Duration::from_secs(60 * 60)),
+    ("/builds", Duration::from_secs(10 * 60)),
+    ("buildNumber", Duration::from_secs(10 * 60)),
+];
+const DEFAULT_METADATA_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// The HTTP client shared by every installer, with an on-disk cache for upstream metadata and downloaded artifacts.
+#[derive(Clone)]
+pub struct Http {
+    client: Client,
+    cache_path: Option<String>,
+    offline: bool,
+    /// URL prefixes and the base URLs to use instead, longest prefix first.
+    upstreams: Vec<(String, Vec<String>)>,
+    /// Holds the progress bars of downloads running at the same time.
+    progress: MultiProgress,
+}
+
+/// A cached response, stored as JSON in the cache directory.
+#[derive(Clone, Deserialize, Serialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// When the entry was last fetched or revalidated, in seconds since the Unix epoch.
+    fetched_at: u64,
+    /// The next page of a paginated response, from its `Link` header.
+    #[serde(default)]
+    next: Option<String>,
+    body: String,
+}
+
+impl Http {
+    /// Uses `client` for every request, caching in the directory at `cache_path` if given.
+    ///
+    /// When `offline` is set, nothing is requested and everything has to come from the cache.
+    pub fn new(client: Client, cache_path: Option<String>, offline: bool) -> Http {
+        Http { client, cache_path, offline, upstreams: vec![], progress: MultiProgress::new() }
+    }
+
+    /// Sends requests for URLs starting with one of the prefixes in `upstreams` to the base URLs it maps to
+    /// instead, trying them in order until one succeeds. See [`Http::mirrors`].
+    pub fn with_upstreams(mut self, upstreams: Vec<(String, Vec<String>)>) -> Http {
+        self.upstreams = upstreams.into_iter().filter(|(_, mirrors)| !mirrors.is_empty()).collect();
+        self.upstreams.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The progress display shared by all clones of this client. Print through [`MultiProgress::suspend`] while downloading.
+    pub fn progress(&self) -> &MultiProgress {
+        &self.progress
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// The URLs to try for `url`, in order, with its prefix replaced by each configured mirror.
+    ///
+    /// `url` itself is only tried if it is listed as one of the mirrors, so an upstream can be replaced entirely.
+    pub fn mirrors(&self, url: &str) -> Vec<String> {
+        let upstream = self.upstreams.iter().find_map(|(prefix, mirrors)| Some((url.strip_prefix(prefix.as_str())?, mirrors)));
+
+        match upstream {
+            Some((path, mirrors)) => mirrors.iter().map(|mirror| mirror.to_string() + path).collect(),
+            None => vec![url.to_string()],
+        }
+    }
+
+    /// The directory downloaded artifacts are kept in, named by the SHA-256 of their content.
+    pub fn artifact_path(&self) -> Option<String> {
+        self.cache_path.as_ref().map(|cache_path| cache_path.to_string() + "/artifacts")
+    }
+
+    /// Returns the cached copy of the artifact downloaded from `url`, if there is one matching `checksum`.
+    pub fn cached_artifact(&self, url: &str, checksum: Option<&Checksum>) -> Option<String> {
+        let artifact_path = self.artifact_path()?;
+        let digest = fs::read_to_string(format!("{}/urls/{}", artifact_path, url_key(url))).ok()?;
+        let blob_path = format!("{}/{}", artifact_path, digest.trim());
+
+        if !Path::new(&blob_path).exists() {
+            return None;
+        }
+
+        match checksum {
+            Some(checksum) if checksum.algorithm == ChecksumAlgorithm::Sha256 => (checksum.value == digest.trim()).then_some(blob_path),
+            Some(checksum) => (file_checksum(&blob_path, checksum.algorithm).ok()? == *checksum).then_some(blob_path),
+            None => Some(blob_path),
+        }
+    }
+
+    /// Moves the file at `path`, downloaded from `url`, into the artifact cache and returns its new location.
+    pub fn store_artifact(&self, url: &str, path: &str) -> Result<String, DownloadError> {
+        let artifact_path = self.artifact_path().ok_or_else(|| DownloadError::NotCached { url: url.to_string() })?;
+        let digest = file_checksum(path, ChecksumAlgorithm::Sha256).map_err(|error| DownloadError::io(path, error))?.value;
+        let blob_path = format!("{}/{}", artifact_path, digest);
+        let urls_path = format!("{}/urls", artifact_path);
+
+        fs::create_dir_all(&urls_path).map_err(|error| DownloadError::io(&urls_path, error))?;
+        fs::rename(path, &blob_path).map_err(|error| DownloadError::io(&blob_path, error))?;
+
+        let index_path = format!("{}/{}", urls_path, url_key(url));
+        fs::write(&index_path, &digest).map_err(|error| DownloadError::io(&index_path, error))?;
+
+        Ok(blob_path)
+    }
+
+    /// Fetches `url` and returns its body, failing on non-success status codes.
+    ///
+    /// Responses are cached. A cached body is returned as is while it is fresh, revalidated with the
+    /// upstream once it is not, and still returned if the upstream cannot be reached or we are offline.
+    pub async fn get_text(&self, url: &str) -> Result<String, DownloadError> {
+        self.get(url, None).await.map(|entry| entry.body)
+    }
+
+    /// Fetches `url` and each page its `Link` header points to, as GitHub's API paginates, and parses every page as JSON.
+    ///
+    /// `accept` is sent as the `Accept` header. Pages are cached like [`Http::get_text`] responses.
+    pub async fn get_json_pages(&self, url: &str, accept: &str) -> Result<Vec<serde_json::Value>, DownloadError> {
+        let mut pages = vec![];
+        let mut visited = vec![];
+        let mut next = Some(url.to_string());
+
+        while let Some(url) = next.filter(|url| !visited.contains(url)) {
+            let entry = self.get(&url, Some(accept)).await?;
+
+            pages.push(serde_json::from_str(&entry.body).map_err(|error| DownloadError::schema(&url, &error.to_string()))?);
+            next = entry.next;
+            visited.push(url);
+        }
+
+        Ok(pages)
+    }
+
+    /// Returns the response for `url`, see [`Http::get_text`].
+    async fn get(&self, url: &str, accept: Option<&str>) -> Result<CacheEntry, DownloadError> {
+        let cached = self.read_cache(url);
+
+        if self.offline {
+            return cached.ok_or_else(|| DownloadError::NotCached { url: url.to_string() });
+        }
+
+        if let Some(entry) = &cached {
+            if now().saturating_sub(entry.fetched_at) < ttl(url).as_secs() {
+                return Ok(entry.clone());
+            }
+        }
+
+        let mirrors = self.mirrors(url);
+        let mut last_error = None;
+
+        for (index, mirror) in mirrors.iter().enumerate() {
+            if let Some(error) = last_error.take() {
+                self.progress.suspend(|| eprintln!("Warning: {}. Trying {} instead.", error, mirror));
+            }
+
+            match self.fetch(mirror, cached.as_ref(), accept).await {
+                Ok(Some(fetched)) => {
+                    let entry = CacheEntry { url: url.to_string(), fetched_at: now(), ..fetched };
+                    self.write_cache(&entry);
+
+                    return Ok(entry);
+                }
+                Ok(None) => {
+                    let mut entry = cached.expect("Only cached responses can be unmodified");
+                    entry.fetched_at = now();
+                    self.write_cache(&entry);
+
+                    return Ok(entry);
+                }
+                Err(error) if index + 1 < mirrors.len() => last_error = Some(error),
+                Err(error) => {
+                    let unavailable = match &error {
+                        DownloadError::Http { status, .. } => status.is_server_error(),
+                        DownloadError::Network(_) => true,
+                        _ => false,
+                    };
+
+                    return if unavailable { self.stale(cached, error) } else { Err(error) };
+                }
+            }
+        }
+
+        unreachable!("Http::mirrors always returns at least one URL")
+    }
+
+    /// Requests `url`, revalidating `cached` if given. Returns `None` if `cached` is still up to date.
+    async fn fetch(&self, url: &str, cached: Option<&CacheEntry>, accept: Option<&str>) -> Result<Option<CacheEntry>, DownloadError> {
+        let mut request = self.client.get(url);
+
+        if let Some(accept) = accept {
+            request = request.header(ACCEPT, accept);
+        }
+
+        if let Some(entry) = cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_MODIFIED && cached.is_some() {
+            return Ok(None);
+        }
+
+        if !status.is_success() {
+            return Err(DownloadError::Http { url: url.to_string(), status });
+        }
+
+        let etag = response.headers().get(ETAG).and_then(|value| value.to_str().ok()).map(|value| value.to_string());
+        let last_modified = response.headers().get(LAST_MODIFIED).and_then(|value| value.to_str().ok()).map(|value| value.to_string());
+        let next = response.headers().get(LINK).and_then(|value| value.to_str().ok()).and_then(next_link);
+        let body = response.text().await?;
+
+        Ok(Some(CacheEntry {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            fetched_at: now(),
+            next,
+            body,
+        }))
+    }
+
+    /// Fetches `url` and parses its body as JSON.
+    pub async fn get_json(&self, url: &str) -> Result<serde_json::Value, DownloadError> {
+        let body = self.get_text(url).await?;
+
+        serde_json::from_str(&body).map_err(|error| DownloadError::schema(url, &error.to_string()))
+    }
+
+    /// Falls back to an outdated cache entry when the upstream is unavailable.
+    fn stale(&self, cached: Option<CacheEntry>, error: DownloadError) -> Result<CacheEntry, DownloadError> {
+        match cached {
+            Some(entry) => {
+                self.progress.suspend(|| eprintln!("Warning: {}. Using cached data from {} minutes ago.", error, now().saturating_sub(entry.fetched_at) / 60));
+                Ok(entry)
+            }
+            None => Err(error),
+        }
+    }
+
+    fn entry_path(&self, url: &str) -> Option<String> {
+        let cache_path = self.cache_path.as_ref()?;
+
+        Some(format!("{}/metadata/{}.json", cache_path, url_key(url)))
+    }
+
+    fn read_cache(&self, url: &str) -> Option<CacheEntry> {
+        let content = fs::read_to_string(self.entry_path(url)?).ok()?;
+        let entry = serde_json::from_str::<CacheEntry>(&content).ok()?;
+
+        (entry.url == url).then_some(entry)
+    }
+
+    /// Saves `entry`. The cache is only an optimization, so failures are ignored.
+    fn write_cache(&self, entry: &CacheEntry) {
+        let (Some(cache_path), Some(entry_path)) = (&self.cache_path, self.entry_path(&entry.url)) else {
+            return;
+        };
+
+        let metadata_path = cache_path.to_string() + "/metadata";
+
+        if !Path::new(&metadata_path).exists() && fs::create_dir_all(&metadata_path).is_err() {
+            return;
+        }
+
+        if let Ok(content) = serde_json::to_string(entry) {
+            let part_path = format!("{}.part", entry_path);
+
+            if fs::write(&part_path, content).is_ok() {
+                let _ = fs::rename(&part_path, &entry_path);
+            }
+        }
+    }
+}
+
+/// A file name for `url`.
+pub(crate) fn url_key(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url.as_bytes()))
+}
+
+/// The target of the `rel="next"` link in a `Link` header, like `<https://…?page=2>; rel="next", <https://…?page=5>; rel="last"`.
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        let is_next = params.split(';').any(|param| param.trim().replace(' ', "") == "rel=\"next\"");
+
+        is_next.then(|| target.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
+
+fn ttl(url: &str) -> Duration {
+    METADATA_TTLS
+        .iter()
+        .find(|(pattern, _)| url.contains(pattern))
+        .map(|(_, ttl)| *ttl)
+        .unwrap_or(DEFAULT_METADATA_TTL)
+}
+
+/// The current time in seconds since the Unix epoch, as recorded in instances, snapshots and cache entries.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
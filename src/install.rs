@@ -0,0 +1,182 @@
+use std::path::Path;
+use crate::config::ConfigFile;
+use crate::checksum::{file_checksum, ChecksumAlgorithm};
+use crate::downloader::{closest_versions, get_published_checksum, Installer};
+use crate::downloaderror::DownloadError;
+use crate::downloaders::java::download_java;
+use crate::http::{now, Http};
+use crate::installplan::{InstallPlan, JavaPlan, ServerPlan};
+use crate::instance::{Instance, INSTANCE_FILE};
+use crate::launch::{create_args_file, create_launch_script};
+use crate::lockfile::{Lockfile, LOCK_FILE};
+use crate::minecraftversion::MinecraftVersion;
+use crate::os::OS;
+
+/// Checks `minecraft_version` against the versions `server_object` can install and returns the exact version to use.
+///
+/// Partial versions like `1.20` resolve to the newest matching release, e.g. `1.20.4`. `None` stays
+/// `None` and means the latest version, as do installers that do not need a version at all.
+/// Unknown versions fail with [`DownloadError::VersionNotFound`] and suggestions of similar versions.
+pub async fn resolve_version(server_object: &dyn Installer, minecraft_version: Option<String>, http: &Http) -> Result<Option<String>, DownloadError> {
+    let Some(minecraft_version) = minecraft_version.filter(|_| server_object.version_required()) else {
+        return Ok(None);
+    };
+
+    let versions = server_object
+        .get_versions(http.clone())
+        .await?
+        .into_iter()
+        .map(|version| version.id)
+        .collect::<Vec<String>>();
+
+    let resolved = MinecraftVersion::resolve(&minecraft_version, &versions)
+        .ok_or_else(|| DownloadError::version_not_found(&minecraft_version, closest_versions(&minecraft_version, &versions)))?;
+
+    Ok(Some(resolved.to_string()))
+}
+
+/// Works out everything installing `server_object` would download and create, without touching the server directory.
+///
+/// `minecraft_version` should already be checked with [`resolve_version`].
+pub async fn plan_install(server_object: &dyn Installer, minecraft_version: Option<String>, ram: i32, config: &ConfigFile, os: &OS, http: &Http) -> Result<InstallPlan, DownloadError> {
+    let server = server_object.resolve(http.clone(), minecraft_version).await?;
+
+    let java_version = config.get_java_version(http, server.minecraft_version.clone()).await?;
+    let (java_install_path, java_path) = locate_java(config, os, java_version);
+    let java_url = config.get_java_download(os.java_key(), java_version).expect("Failed to get Java download from config");
+    let installed = Path::new(&java_path).exists();
+    let java_checksum = match get_published_checksum(http, &java_url, ".sha256.txt", ChecksumAlgorithm::Sha256).await {
+        Ok(checksum) => checksum,
+        Err(_) if installed => None,
+        Err(error) => return Err(error),
+    };
+
+    Ok(InstallPlan {
+        distribution: server_object.get_name(),
+        files: plan_files(&server, os),
+        server,
+        java: JavaPlan {
+            major: java_version,
+            url: java_url,
+            checksum: java_checksum,
+            installed,
+            install_path: java_install_path,
+            executable: java_path,
+        },
+        ram,
+    })
+}
+
+/// Plans installing exactly what `lockfile` describes, without asking any upstream what is newest.
+///
+/// Only the Java location comes from the config. Lockfiles are tied to the platform their runtime was downloaded for.
+pub fn plan_locked_install(server_object: &dyn Installer, lockfile: &Lockfile, ram: i32, config: &ConfigFile, os: &OS) -> Result<InstallPlan, DownloadError> {
+    if lockfile.java.platform != os.java_key() {
+        return Err(DownloadError::invalid_file(LOCK_FILE, &format!("it was created on {} but this machine is {}", lockfile.java.platform, os.java_key())));
+    }
+
+    let (java_install_path, java_path) = locate_java(config, os, lockfile.java.major);
+
+    Ok(InstallPlan {
+        distribution: server_object.get_name(),
+        server: lockfile.server.clone(),
+        java: JavaPlan {
+            major: lockfile.java.major,
+            url: lockfile.java.url.clone(),
+            checksum: lockfile.java.checksum.clone(),
+            installed: Path::new(&java_path).exists(),
+            install_path: java_install_path,
+            executable: java_path,
+        },
+        ram,
+        files: plan_files(&lockfile.server, os),
+    })
+}
+
+/// Returns the directory runtimes are extracted into and the executable of Java `major` inside it.
+fn locate_java(config: &ConfigFile, os: &OS, major: i32) -> (String, String) {
+    let java_install_path = config.get_java_install_path().expect("Failed to get Java path from config");
+    let java_path = java_install_path.to_string() + &config.get_java_path(os.to_string(), major).expect("Failed to get Java path from config");
+
+    (java_install_path, java_path)
+}
+
+/// Lists the files an install of `server` creates, including our own.
+fn plan_files(server: &ServerPlan, os: &OS) -> Vec<String> {
+    let mut files = server.files.clone();
+    let launch_file = if os == &OS::Windows { "launch.bat" } else { "launch.sh" };
+
+    for file in [launch_file, "user_jvm_args.txt", INSTANCE_FILE, LOCK_FILE] {
+        if !files.iter().any(|existing| existing == file) {
+            files.push(file.to_string());
+        }
+    }
+
+    files
+}
+
+/// Carries out `plan`, made by [`plan_install`] for `server_object`, in the current directory.
+///
+/// This downloads Java if needed while downloading the server, then builds the server, writes the launch script and
+/// records what was installed in [`INSTANCE_FILE`] and [`LOCK_FILE`]. The EULA is not accepted, see [`crate::launch::accept_eula`].
+pub async fn execute_plan(server_object: &dyn Installer, plan: &InstallPlan, os: &OS, http: &Http) -> Result<(), DownloadError> {
+    println!("Beginning download...");
+    println!("Using Java {}", plan.java.major);
+
+    match (&plan.server.minecraft_version, &plan.server.version, &plan.server.build) {
+        (Some(minecraft_version), _, Some(build)) => println!("Using {} {} with build {}.", plan.distribution, minecraft_version, build),
+        (Some(minecraft_version), Some(version), None) => println!("Using game version {} with {} version {}.", minecraft_version, plan.distribution, version),
+        (Some(minecraft_version), None, None) => println!("Using version {}.", minecraft_version),
+        (None, Some(version), _) => println!("Using {} version {}.", plan.distribution, version),
+        (None, None, _) => {}
+    }
+
+    tokio::try_join!(
+        download_java(http, &plan.java.install_path, &plan.java.executable, &plan.java.url, plan.java.checksum.as_ref(), os),
+        server_object.download(http.clone(), &plan.server),
+    )?;
+
+    let artifact_sha256 = file_checksum(&plan.server.artifact.path, ChecksumAlgorithm::Sha256).map_err(|error| DownloadError::io(&plan.server.artifact.path, error))?;
+
+    server_object.build(plan.java.executable.clone(), &plan.server).await?;
+
+    if server_object.custom_script() {
+        create_args_file(plan.ram);
+    } else {
+        create_launch_script(Some(plan.java.executable.as_str()), plan.java.major, os, plan.ram);
+    }
+
+    let existing = Instance::load().filter(|instance| instance.distribution == server_object.get_id());
+
+    Instance {
+        distribution: server_object.get_id(),
+        minecraft_version: plan.server.minecraft_version.clone(),
+        version: plan.server.version.clone(),
+        build: plan.server.build.clone(),
+        artifact_sha256: Some(artifact_sha256.value.clone()),
+        java_major: Some(plan.java.major),
+        java_path: Some(plan.java.executable.clone()),
+        ram: Some(plan.ram),
+        port: existing.as_ref().and_then(|instance| instance.port),
+        installed_at: existing.and_then(|instance| instance.installed_at).or(Some(now())),
+        updated_at: None,
+    }.save()?;
+
+    Lockfile::new(server_object, plan, artifact_sha256, os).save()?;
+
+    Ok(())
+}
+
+/// Installs a server into the current directory, see [`plan_install`] and [`execute_plan`].
+///
+/// `minecraft_version` should already be checked with [`resolve_version`].
+pub async fn install_server(server_object: &dyn Installer, minecraft_version: Option<String>, ram: i32, config: &ConfigFile, os: &OS, http: &Http) -> Result<(), DownloadError> {
+    let plan = plan_install(server_object, minecraft_version, ram, config, os, http).await?;
+
+    execute_plan(server_object, &plan, os, http).await
+}
+
+/// Returns the installer used for the server in the current directory, if one was installed.
+pub fn get_installed_server(options: &[Box<dyn Installer>]) -> Option<&dyn Installer> {
+    Instance::load()?.installer(options)
+}
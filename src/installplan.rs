@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+use crate::checksum::Checksum;
+
+/// Everything an install will download and create, decided before anything is written to disk.
+#[derive(Clone, Debug, Serialize)]
+pub struct InstallPlan {
+    pub distribution: String,
+    pub server: ServerPlan,
+    pub java: JavaPlan,
+    /// The amount of RAM given to the server, in gigabytes.
+    pub ram: i32,
+    /// Files and directories the install creates in the server directory.
+    pub files: Vec<String>,
+}
+
+/// What an [`Installer`](crate::downloader::Installer) will download, as decided by its `resolve` step.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServerPlan {
+    /// The Minecraft version the server runs, if the distribution is tied to one.
+    pub minecraft_version: Option<String>,
+    /// The distribution's own version when it differs from the Minecraft version, e.g. the Forge or Velocity version.
+    pub version: Option<String>,
+    /// The build number of that version, for distributions that publish builds.
+    pub build: Option<String>,
+    pub artifact: Artifact,
+    /// Files and directories left in the server directory once the server is built.
+    pub files: Vec<String>,
+}
+
+/// A file to download.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Artifact {
+    pub url: String,
+    /// Where the file is saved, relative to the server directory.
+    pub path: String,
+    /// The checksum the upstream publishes for the file, if any.
+    pub checksum: Option<Checksum>,
+}
+
+/// The Java runtime a server needs.
+#[derive(Clone, Debug, Serialize)]
+pub struct JavaPlan {
+    pub major: i32,
+    pub url: String,
+    /// The directory runtimes are extracted into.
+    pub install_path: String,
+    /// The Java executable inside the runtime.
+    pub executable: String,
+    /// The checksum the upstream publishes for the runtime archive, if any.
+    pub checksum: Option<Checksum>,
+    /// Whether the runtime is already there, in which case nothing is downloaded.
+    pub installed: bool,
+}
+
+impl Display for InstallPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Distribution:      {}", self.distribution)?;
+        writeln!(f, "Minecraft version: {}", self.server.minecraft_version.as_deref().unwrap_or("-"))?;
+
+        if let Some(version) = &self.server.version {
+            writeln!(f, "Version:           {}", version)?;
+        }
+
+        if let Some(build) = &self.server.build {
+            writeln!(f, "Build:             {}", build)?;
+        }
+
+        writeln!(f, "Download:          {} -> {}", self.server.artifact.url, self.server.artifact.path)?;
+        writeln!(f, "Checksum:          {}", self.server.artifact.checksum.as_ref().map(|checksum| checksum.to_string()).unwrap_or("-".to_string()))?;
+
+        if self.java.installed {
+            writeln!(f, "Java:              {} (installed at {})", self.java.major, self.java.executable)?;
+        } else {
+            writeln!(f, "Java:              {} ({} -> {})", self.java.major, self.java.url, self.java.install_path)?;
+        }
+
+        writeln!(f, "RAM:               {}G", self.ram)?;
+        write!(f, "Files:             {}", self.files.join(", "))
+    }
+}
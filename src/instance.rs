@@ -0,0 +1,109 @@
+use std::fs;
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use crate::downloader::Installer;
+use crate::downloaderror::DownloadError;
+use crate::downloaders::{find_installer, installers};
+use crate::http::now;
+
+/// The file describing the server installed in the current directory.
+pub const INSTANCE_FILE: &str = "msi-instance.toml";
+/// The file older versions used instead of [`INSTANCE_FILE`], holding only the installer's name.
+const LEGACY_FILE: &str = "selected_cache.txt";
+
+/// What is installed in a server directory, stored in [`INSTANCE_FILE`].
+///
+/// Instances migrated from `selected_cache.txt` only know their distribution until they are reinstalled.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Instance {
+    /// The [`Installer::get_id`] of the installer used.
+    pub distribution: String,
+    pub minecraft_version: Option<String>,
+    /// The distribution's own version when it differs from the Minecraft version, e.g. the Forge version.
+    pub version: Option<String>,
+    pub build: Option<String>,
+    /// The SHA-256 of the downloaded server jar or installer.
+    pub artifact_sha256: Option<String>,
+    pub java_major: Option<i32>,
+    pub java_path: Option<String>,
+    /// The RAM given to the server, in gigabytes.
+    pub ram: Option<i32>,
+    pub port: Option<u16>,
+    /// When the server was first installed, in seconds since the Unix epoch.
+    pub installed_at: Option<u64>,
+    /// When the server was last installed or changed, in seconds since the Unix epoch.
+    pub updated_at: Option<u64>,
+}
+
+impl Instance {
+    /// Reads the instance in the current directory, migrating `selected_cache.txt` if that is all there is.
+    pub fn load() -> Option<Instance> {
+        if let Ok(content) = fs::read_to_string(INSTANCE_FILE) {
+            return match toml::from_str::<Instance>(&content) {
+                Ok(instance) => Some(instance),
+                Err(error) => {
+                    eprintln!("Warning: {} could not be read: {}", INSTANCE_FILE, error);
+                    None
+                }
+            };
+        }
+
+        let name = fs::read_to_string(LEGACY_FILE).ok()?;
+        let installer = find_installer(&installers(), name.trim())?.get_id();
+        let installed_at = fs::metadata(LEGACY_FILE)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs());
+
+        let instance = Instance {
+            distribution: installer,
+            installed_at,
+            updated_at: installed_at,
+            ..Instance::default()
+        };
+
+        if let Err(error) = instance.save() {
+            eprintln!("Warning: {} could not be migrated: {}", LEGACY_FILE, error);
+            return Some(instance);
+        }
+
+        if let Err(error) = fs::remove_file(LEGACY_FILE) {
+            eprintln!("Warning: {} could not be removed: {}", LEGACY_FILE, error);
+        }
+
+        println!("Migrated {} to {}.", LEGACY_FILE, INSTANCE_FILE);
+
+        Some(instance)
+    }
+
+    /// Reads the instance in `directory` as it is, without migrating anything.
+    pub fn read(directory: &str) -> Option<Instance> {
+        let content = fs::read_to_string(format!("{}/{}", directory, INSTANCE_FILE)).ok()?;
+
+        toml::from_str(&content).ok()
+    }
+
+    /// Writes the instance to the current directory, setting `updated_at`.
+    pub fn save(&self) -> Result<(), DownloadError> {
+        let instance = Instance { updated_at: Some(now()), ..self.clone() };
+        let content = toml::to_string(&instance).map_err(|error| DownloadError::invalid_file(INSTANCE_FILE, &error.to_string()))?;
+
+        fs::write(INSTANCE_FILE, content).map_err(|error| DownloadError::io(INSTANCE_FILE, error))
+    }
+
+    /// Returns the installer this instance was installed with.
+    pub fn installer<'a>(&self, installers: &'a [Box<dyn Installer>]) -> Option<&'a dyn Installer> {
+        find_installer(installers, &self.distribution)
+    }
+}
+
+/// Applies `change` to the instance in the current directory and saves it. Does nothing if there is none.
+pub fn update_instance(change: impl FnOnce(&mut Instance)) -> Result<(), DownloadError> {
+    if let Some(mut instance) = Instance::load() {
+        change(&mut instance);
+        instance.save()?;
+    }
+
+    Ok(())
+}
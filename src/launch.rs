@@ -0,0 +1,226 @@
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::net::SocketAddrV4;
+use std::process::{Command, Stdio};
+use crate::downloader::Installer;
+use crate::os::OS;
+
+/// Writes `launch.sh` (or `launch.bat` on Windows) along with `user_jvm_args.txt`.
+///
+/// When `java_path` is `None`, the Java executable of the existing launch script is kept.
+pub fn create_launch_script(java_path: Option<&str>, java_version: i32, os: &OS, ram: i32) {
+    println!("Creating launch script...");
+    create_args_file(ram);
+
+    let file_name = if os == &OS::Windows {
+        "./launch.bat"
+    } else {
+        "./launch.sh"
+    };
+
+    let args_str = if java_version == 8 {
+        let args_vec = fs::read_to_string("./user_jvm_args.txt").expect("Failed to read user_jvm_args.txt");
+        let args_vec = args_vec.split_whitespace().collect::<Vec<&str>>();
+
+        let mut args = String::new();
+
+        for arg in args_vec {
+            if !arg.starts_with('#') {
+                args += (" ".to_string() + arg).as_str()
+            }
+        }
+
+        args
+    } else {
+        "@user_jvm_args.txt".to_string()
+    };
+
+    match java_path {
+        None => {
+            let original_content = fs::read_to_string(file_name).expect("Failed to read launch file");
+            let original_java_path = original_content.lines()
+                .filter(|line| !line.starts_with('#') && !line.starts_with("REM" ) && !line.starts_with('@'))
+                .collect::<Vec<&str>>().first().unwrap().split_whitespace().collect::<Vec<&str>>()[0];
+
+            fs::remove_file(file_name).expect("Failed to remove launch file");
+
+            let file = File::create(file_name).unwrap();
+            let mut file = BufWriter::new(file);
+
+            if os == &OS::Windows {
+                file.write_all(
+                    format!(
+                        "@echo off\n\"{}\" {} -jar server.jar",
+                        original_java_path.replace('"', ""),
+                        args_str
+                    )
+                        .as_bytes(),
+                ).expect("Failed to write to launch file");
+            } else {
+                file.write_all(
+                    format!(
+                        "#!#!/usr/bin/env sh\n\"{}\" {} -jar server.jar",
+                        original_java_path.replace('"', ""),
+                        args_str
+                    )
+                        .as_bytes(),
+                ).expect("Failed to write to launch file");
+            }
+        }
+        Some(java_path) => {
+            let file = File::create(file_name).unwrap();
+            let mut file = BufWriter::new(file);
+
+            file.write_all(
+                format!(
+                    "\"{}\" {} -jar server.jar",
+                    java_path,
+                    args_str
+                )
+                    .as_bytes(),
+            )
+                .expect("Failed to write to launch file");
+        }
+    }
+
+    if os != &OS::Windows {
+        Command::new("chmod")
+            .arg("+x")
+            .arg("./launch.sh")
+            .output()
+            .expect("Failed to chmod launch.sh");
+    }
+
+    println!("Launch script was created!");
+}
+
+/// Creates `user_jvm_args.txt`, or updates the maximum heap size of an existing one.
+pub fn create_args_file(ram: i32) {
+    match File::open("./user_jvm_args.txt") {
+        Ok(_) => {
+            let mut file = File::open("user_jvm_args.txt").expect("Failed to open user_jvm_args.txt");
+            let mut content = String::new();
+            file.read_to_string(&mut content).expect("Failed to read user_jvm_args.txt");
+
+            let new_script = if content.contains("-Xmx") {
+                content.lines().collect::<Vec<&str>>().iter().map(|s| {
+                    if s.contains("-Xmx") && !s.starts_with('#') {
+                        format!("-Xmx{}G", ram)
+                    } else {
+                        s.to_string()
+                    }
+                }).collect::<Vec<String>>().join("\n")
+            } else {
+                format!("{} -Xms1024M -Xmx{}G", content, ram)
+            };
+
+            fs::write("user_jvm_args.txt", new_script).expect("Failed to write to user_jvm_args.txt");
+        }
+        Err(_) => {
+            let file = File::create("./user_jvm_args.txt").unwrap();
+            let mut file = BufWriter::new(file);
+
+            file.write_all(
+                format!(
+                    "-Xms1024M -Xmx{}G",
+                    ram
+                )
+                    .as_bytes(),
+            )
+                .expect("Failed to write to user_jvm_args.txt");
+        }
+    };
+}
+
+/// Replaces `user_jvm_args.txt` with the heap settings for `ram` followed by `jvm_flags`, one per line.
+///
+/// Returns whether the file changed.
+pub fn write_args_file(ram: i32, jvm_flags: &[String]) -> bool {
+    let mut lines = vec!["-Xms1024M".to_string(), format!("-Xmx{}G", ram)];
+    lines.extend(jvm_flags.iter().cloned());
+
+    let content = lines.join("\n");
+
+    if fs::read_to_string("./user_jvm_args.txt").is_ok_and(|existing| existing == content) {
+        return false;
+    }
+
+    fs::write("./user_jvm_args.txt", content).expect("Failed to write to user_jvm_args.txt");
+    true
+}
+
+/// Returns the maximum heap size in `user_jvm_args.txt`, in gigabytes.
+pub fn read_ram() -> Option<i32> {
+    let content = fs::read_to_string("./user_jvm_args.txt").ok()?;
+
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split_whitespace())
+        .find_map(|arg| arg.strip_prefix("-Xmx")?.strip_suffix(['G', 'g'])?.parse::<i32>().ok())
+}
+
+/// Accepts the Minecraft EULA by writing `eula.txt`.
+pub async fn accept_eula() {
+    println!("Checking EULA...");
+    let file = File::create("./eula.txt").unwrap();
+    let mut file = BufWriter::new(file);
+
+    file.write_all("eula=true".as_bytes()).unwrap();
+}
+
+/// Starts the server through its launch script and streams its output until it stops.
+///
+/// Returns the exit code of the server process, if it had one.
+pub async fn run_launch_file(os: &OS, server: &dyn Installer) -> Option<i32> {
+    println!("Starting server...");
+
+    let mut content = String::new();
+
+    if os == &OS::Windows {
+        File::open("./launch.bat").expect("Failed to open launch.bat").read_to_string(&mut content).expect("Failed to read launch.bat");
+    } else {
+        File::open("./launch.sh").expect("Failed to open launch.sh").read_to_string(&mut content).expect("Failed to read launch.sh");
+    };
+
+    let args_no_comments = content.lines().filter(
+        |line| !line.starts_with('#') && !line.starts_with("REM") && !line.starts_with('@')).collect::<Vec<&str>>();
+
+    let java_path = &args_no_comments.clone().first().unwrap().split_whitespace().collect::<Vec<&str>>()[0].replace('"', "");
+    let args = &args_no_comments.first().unwrap().split_whitespace().collect::<Vec<&str>>()[1..];
+
+    println!("Starting server with Java path: {}", java_path);
+
+    let mut process = Command::new(java_path)
+        .args(args.iter().map(|s| s.replace('"', "")).collect::<Vec<String>>())
+        .stdout(Stdio::piped())
+        .spawn().expect("Failed to start server");
+
+    let out = process.stdout.take()
+        .expect("Failed to capture standard output");
+    let reader = BufReader::new(out);
+
+    let mut address: Option<SocketAddrV4> = None;
+    for line in reader.lines().map_while(Result::ok) {
+        println!("{}", line);
+
+        if server.startup_message(line.clone()).await.is_some() {
+            address = server.startup_message(line.clone()).await;
+        }
+
+        if line.contains("Done (") || line.contains("Listening on /") {
+            println!();
+            println!("Server is ready!");
+            println!("To safely stop the server, type 'stop' and press enter.");
+
+            if let Some(address) = address {
+                println!("If you port forwarded your server, other people can join using the following IP: {}", address);
+            }
+
+            println!();
+        }
+    }
+
+    process.wait().expect("Failed to wait for server to finish").code()
+}
@@ -0,0 +1,29 @@
+//! Downloads, installs and runs Minecraft server distributions.
+//!
+//! Every supported distribution implements [`downloader::Installer`], and the built-in ones are
+//! available through [`downloaders::installers`]. [`install::install_server`] ties them together
+//! with Java provisioning and launch script generation, which is what the command line front-end
+//! uses as well.
+
+pub mod backup;
+pub mod changelog;
+pub mod checksum;
+pub mod config;
+pub mod downloader;
+pub mod downloaderror;
+pub mod downloaders;
+pub mod history;
+pub mod http;
+pub mod install;
+pub mod instance;
+pub mod installplan;
+pub mod launch;
+pub mod lockfile;
+pub mod manifest;
+pub mod minecraftversion;
+pub mod os;
+pub mod properties;
+pub mod registry;
+pub mod servertype;
+pub mod upgrade;
+pub mod versioninfo;
@@ -0,0 +1,67 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+use crate::checksum::Checksum;
+use crate::downloader::Installer;
+use crate::downloaderror::DownloadError;
+use crate::installplan::{InstallPlan, ServerPlan};
+use crate::os::OS;
+
+/// The file recording exactly what was installed in a server directory, so it can be installed again elsewhere.
+pub const LOCK_FILE: &str = "msi-lock.toml";
+
+/// The resolved artifacts of an install, stored in [`LOCK_FILE`].
+///
+/// Unlike [`crate::instance::Instance`], this is meant to be copied to other machines and installed with
+/// `install --locked`, which downloads these exact URLs and fails if any of them no longer match their checksum.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Lockfile {
+    /// The [`Installer::get_id`] of the installer used.
+    pub distribution: String,
+    /// The server as resolved, with the SHA-256 of the downloaded artifact as its checksum.
+    pub server: ServerPlan,
+    pub java: LockedJava,
+}
+
+/// The Java runtime a [`Lockfile`] was installed with.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LockedJava {
+    pub major: i32,
+    /// The [`OS::java_key`] the runtime was downloaded for.
+    pub platform: String,
+    pub url: String,
+    /// The checksum Adoptium publishes for the runtime archive, if it could be fetched.
+    pub checksum: Option<Checksum>,
+}
+
+impl Lockfile {
+    /// Records `plan` after it was installed, pinning the server artifact to `artifact_sha256`.
+    pub fn new(server_object: &dyn Installer, plan: &InstallPlan, artifact_sha256: Checksum, os: &OS) -> Lockfile {
+        let mut server = plan.server.clone();
+        server.artifact.checksum = Some(artifact_sha256);
+
+        Lockfile {
+            distribution: server_object.get_id(),
+            server,
+            java: LockedJava {
+                major: plan.java.major,
+                platform: os.java_key(),
+                url: plan.java.url.clone(),
+                checksum: plan.java.checksum.clone(),
+            },
+        }
+    }
+
+    /// Reads the lockfile in the current directory.
+    pub fn load() -> Result<Lockfile, DownloadError> {
+        let content = fs::read_to_string(LOCK_FILE).map_err(|error| DownloadError::io(LOCK_FILE, error))?;
+
+        toml::from_str(&content).map_err(|error| DownloadError::invalid_file(LOCK_FILE, &error.to_string()))
+    }
+
+    /// Writes the lockfile to the current directory.
+    pub fn save(&self) -> Result<(), DownloadError> {
+        let content = toml::to_string(self).map_err(|error| DownloadError::invalid_file(LOCK_FILE, &error.to_string()))?;
+
+        fs::write(LOCK_FILE, content).map_err(|error| DownloadError::io(LOCK_FILE, error))
+    }
+}
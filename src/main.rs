@@ -1,85 +1,60 @@
-extern crate core;
-
-pub mod downloaders;
-pub mod config;
-pub mod downloader;
-pub mod downloaderror;
-pub mod os;
-mod servertype;
-
-use flate2::read::GzDecoder;
-use reqwest::Client;
-use std::{env, fs, panic};
+mod cli;
+
+use clap::Parser;
+use std::{fs, panic};
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Read, stdin, stdout, Write};
-use std::net::SocketAddrV4;
-use std::ops::{Deref};
-use std::path::Path;
-use std::process::{Command, exit, Stdio};
-use tar::Archive;
-use crate::downloader::Installer;
-use crate::downloaders::fabric::Fabric;
-use crate::downloaders::bungeecord::BungeeCord;
-use crate::downloaders::forge::Forge;
-use crate::downloaders::geyser::Geyser;
-use crate::downloaders::java::download_java;
-use crate::downloaders::neoforge::NeoForge;
-use crate::downloaders::paper::Paper;
-use crate::downloaders::purpur::Purpur;
-use crate::downloaders::vanilla::Vanilla;
-use crate::downloaders::velocity::Velocity;
-use crate::downloaders::waterfall::Waterfall;
-use crate::os::OS;
-use crate::servertype::ServerType::{Proxy, Server};
+use std::io::{BufWriter, stdin, stdout, Write};
+use std::ops::Deref;
+use std::process::exit;
+use std::time::Duration;
+use futures_util::future::join_all;
+use indicatif::{ProgressBar, ProgressStyle};
+use minecraft_server_installer::config::ConfigFile;
+use minecraft_server_installer::downloader::Installer;
+use minecraft_server_installer::downloaderror::DownloadError;
+use minecraft_server_installer::downloaders::installers;
+use minecraft_server_installer::http::Http;
+use minecraft_server_installer::install::{get_installed_server, install_server, resolve_version};
+use minecraft_server_installer::instance::{update_instance, Instance};
+use minecraft_server_installer::launch::{accept_eula, create_args_file, run_launch_file};
+use minecraft_server_installer::minecraftversion::MinecraftVersion;
+use minecraft_server_installer::os::OS;
+use minecraft_server_installer::properties::set_server_port;
+use minecraft_server_installer::registry::Registry;
+use minecraft_server_installer::servertype::ServerType::{Proxy, Server};
+use minecraft_server_installer::upgrade::{check_for_update, execute_upgrade, plan_upgrade, Update, UpgradeTarget};
+use minecraft_server_installer::versioninfo::{VersionInfo, VersionKind};
+use tokio::time::timeout;
+
+/// How long the menu waits for each source's version list.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() {
-    prepare_hook();
+    let cli = cli::Cli::parse();
 
-    let downloaders: Vec<Box<dyn Installer>> = vec![
-        Box::new(Vanilla {}),
-        Box::new(Paper {}),
-        Box::new(Purpur {}),
-        Box::new(Fabric {}),
-        Box::new(Forge {}),
-        Box::new(NeoForge {}),
-        Box::new(Geyser {}),
-        Box::new(BungeeCord {}),
-        Box::new(Velocity {}),
-        Box::new(Waterfall {}),
-    ];
-
-    let is_arm = env::consts::ARCH.contains("arch64") || env::consts::ARCH.contains("arm");
-    let os = if cfg!(target_os = "macos") {
-        OS::MacOS
-    } else if cfg!(target_os = "linux") {
-        OS::Linux
-    } else if cfg!(target_os = "windows") {
-        OS::Windows
-    } else {
-        panic!("Unsupported OS.");
-    };
+    if let Some(command) = cli.command {
+        exit(cli::execute(command, cli.offline, cli.instance).await);
+    }
 
-    let config = config::ConfigFile {
-        path: if os == OS::Windows {
-            env::var("APPDATA").unwrap_or("./".to_string()) + "\\MinecraftServerInstaller"
-        } else if os == OS::Linux {
-            env::var("XDG_CONFIG_HOME").unwrap_or("./".to_string()) + "/MinecraftServerInstaller"
-        } else {
-            env::var("HOME").unwrap_or("./".to_string()) + "/Library/Application Support/MinecraftServerInstaller"
-        }
-    };
+    prepare_hook();
 
-    if !Path::new(&(config.path.to_string() + "/msi-config.toml")).exists() {
-        if !Path::new(&config.path).exists() {
-            fs::create_dir_all(&config.path).expect("Failed to create config directory");
+    let downloaders = installers();
+    let os = OS::current();
+    let config = ConfigFile::load(&os);
+    let http = match config.http(cli.offline) {
+        Ok(http) => http,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            exit(cli::EXIT_FAILURE);
         }
+    };
 
-        config.create();
+    if let Err(error) = Registry::load(&os).enter(cli.instance.clone()) {
+        eprintln!("Error: {}", error);
+        exit(cli::EXIT_USAGE);
     }
 
-    config.test();
-
     println!("Welcome to the Minecraft Server Installer!");
     println!("This tool will help you set up a Minecraft server with ease.");
     println!();
@@ -102,25 +77,40 @@ async fn main() {
             ready = true;
         }
 
-        if get_selected_from_cache(&downloaders).is_none() {
+        if get_installed_server(&downloaders).is_none() {
             ready = false;
         }
 
         if ready {
-            let server_object = get_selected_from_cache(&downloaders).unwrap();
+            let server_object = get_installed_server(&downloaders).unwrap();
 
             println!("A valid server file was found.");
+
+            if let Some(update) = find_update(server_object, &http).await {
+                println!("An update for {} is available: {}.", server_object.get_name(), update);
+                print!("Would you like to upgrade now? Your server will be backed up first. (y/n): ");
+
+                if yes_or_no() {
+                    upgrade(server_object, &config, &os, &http, false).await;
+                    wait_for_enter("continue");
+                    continue
+                }
+
+                println!();
+            }
+
             println!("1. Run the server.");
             println!("2. Change amount of RAM allocated to the server.");
             println!("3. Change the running port of the server.");
-            println!("4. Replace your server file.");
+            println!("4. Upgrade the server to its newest build.");
+            println!("5. Replace your server file.");
             println!();
-            print!("Enter the number of the action you want to take: (1-4): ");
+            print!("Enter the number of the action you want to take: (1-5): ");
 
             let mut selection = user_input();
 
             while match selection.parse::<i32>() {
-                Ok(value) => !(1..=4).contains(&value),
+                Ok(value) => !(1..=5).contains(&value),
                 Err(_) => true,
             } {
                 print!("Please enter a valid number: ");
@@ -131,6 +121,7 @@ async fn main() {
 
             if num == 1 {
                 run_launch_file(&os, server_object).await;
+                wait_for_enter("continue");
                 continue
             } else if num == 2 {
                 change_ram();
@@ -138,55 +129,37 @@ async fn main() {
             } else if num == 3 {
                 change_port(server_object);
                 continue
-            } else if num == 4 && fs::remove_file("./server.jar").is_ok() {
+            } else if num == 4 {
+                upgrade(server_object, &config, &os, &http, true).await;
+                wait_for_enter("continue");
+                continue
+            } else if num == 5 && fs::remove_file("./server.jar").is_ok() {
                 println!("Server file was removed.");
             }
         }
 
-        let java_key = if is_arm {
-            os.to_string() + "_arm"
-        } else {
-            os.to_string()
-        };
-
-        let client = Client::new();
-
         let mut out_string: Vec<String> = vec![];
 
-        println!("Gathering server information...");
+        let summaries = gather_versions(&downloaders, &http).await;
 
         out_string.push("".to_string());
 
         out_string.push("Servers:".to_string());
-        let server_downloaders = downloaders.iter().filter(|downloader| downloader.get_type() == Server).collect::<Vec<&Box<dyn Installer>>>();
-        for (mut index, downloader) in server_downloaders.iter().enumerate() {
-            let mut versions = downloader.get_versions(client.clone()).await;
-
-            if versions.len() > 20 {
-                versions = vec![format!("Too many versions, type {}V to list.", index + 1)];
-            }
-
-            versions.reverse();
-
+        let server_downloaders = downloaders.iter().zip(&summaries).filter(|(downloader, _)| downloader.get_type() == Server).collect::<Vec<(&Box<dyn Installer>, &String)>>();
+        for (mut index, (downloader, versions)) in server_downloaders.iter().enumerate() {
             index += 1;
-            out_string.push(format!("  {}. {} - {} - [{}]", index, downloader.get_name(), downloader.get_description(), versions.join(", ")));
+
+            out_string.push(format!("  {}. {} - {} - [{}]", index, downloader.get_name(), downloader.get_description(), versions));
         }
 
         out_string.push("".to_string());
 
         out_string.push("Proxies:".to_string());
-        let proxy_downloaders = downloaders.iter().filter(|downloader| downloader.get_type() == Proxy).collect::<Vec<&Box<dyn Installer>>>();
-        for (mut index, downloader) in proxy_downloaders.iter().enumerate() {
-            let mut versions = downloader.get_versions(client.clone()).await;
+        let proxy_downloaders = downloaders.iter().zip(&summaries).filter(|(downloader, _)| downloader.get_type() == Proxy).collect::<Vec<(&Box<dyn Installer>, &String)>>();
+        for (mut index, (downloader, versions)) in proxy_downloaders.iter().enumerate() {
+            index += server_downloaders.len() + 1;
 
-            if versions.len() > 20 {
-                versions = vec![format!("Too many versions, type {}V to list.", index + 1)];
-            }
-
-            versions.reverse();
-
-            index += 1;
-            out_string.push(format!("  {}. {} - {} - [{}]", index + server_downloaders.len(), downloader.get_name(), downloader.get_description(), versions.join(", ")));
+            out_string.push(format!("  {}. {} - {} - [{}]", index, downloader.get_name(), downloader.get_description(), versions));
         }
 
         println!("What kind of server do you want to run?");
@@ -205,38 +178,18 @@ async fn main() {
             Ok(value) => !(1..=total_types).contains(&value),
             Err(_) => true,
         } {
-            if server_type.ends_with('V') || server_type.ends_with('v') {
-                let index = server_type.replace(['V', 'v'], "").parse::<usize>().expect("Failed to parse index");
-                let downloader = downloaders.get(index - 1).expect("Failed to get downloader");
-
-                let versions = downloader.get_versions(client.clone()).await;
-                let mut out_string: Vec<String> = vec![];
-
-                for version in versions {
-                    let start = if version.contains('.') {
-                        let version_string = version.split('.').collect::<Vec<&str>>().first().unwrap().to_string() + "." + version.split('.').collect::<Vec<&str>>().get(1).unwrap().split('-').collect::<Vec<&str>>().first().unwrap();
-                        version_string
-                    } else {
-                        version.split('w').collect::<Vec<&str>>().first().unwrap().to_string()
-                    };
-
-                    if out_string.iter().any(|s| s.starts_with(start.as_str())) {
-                        out_string = out_string.iter().map(|s| {
-                            if s.starts_with(start.as_str()) {
-                                format!("{}, {}", s, version)
-                            } else {
-                                s.to_string()
-                            }
-                        }).collect::<Vec<String>>();
-                    } else {
-                        out_string.push(version);
-                    }
-                }
+            let listing = server_type.to_uppercase();
+            let show_all = listing.ends_with("VA");
+
+            let index = listing.strip_suffix("VA").or(listing.strip_suffix('V')).and_then(|index| index.parse::<usize>().ok());
 
-                println!("Versions for {}:", downloader.get_name());
-                for version in out_string {
-                    println!("{}", version);
-                    println!();
+            if let Some((index, downloader)) = index.and_then(|index| Some((index, downloaders.get(index.checked_sub(1)?)?))) {
+                match downloader.get_versions(http.clone()).await {
+                    Ok(mut versions) => {
+                        config.set_java_versions(&mut versions);
+                        print_versions(downloader.get_name(), versions, index, show_all)
+                    }
+                    Err(error) => println!("Versions for {} are unavailable: {}", downloader.get_name(), error),
                 }
 
                 print!("Enter the number of the server you want to run: (1-{}): ", downloaders.len());
@@ -254,44 +207,50 @@ async fn main() {
             println!();
             print!("What version of Minecraft do you want to run? Type latest for the latest version: ");
 
-            let input = user_input();
+            loop {
+                let input = user_input();
+                let input = if input == "latest" { None } else { Some(input) };
 
-            if input == "latest" {
-                None
-            } else {
-                Some(input)
+                match resolve_version(server_object.deref(), input, &http).await {
+                    Ok(minecraft_version) => break Ok(minecraft_version),
+                    Err(DownloadError::VersionNotFound { version, suggestions }) => {
+                        println!("{} {} does not exist.", server_object.get_name(), version);
+
+                        if !suggestions.is_empty() {
+                            println!("Did you mean {}?", suggestions.join(", "));
+                        }
+
+                        print!("Please enter a valid version: ");
+                    }
+                    Err(error) => break Err(error),
+                }
             }
         } else {
-            None
+            Ok(None)
         };
 
-        println!("Beginning download...");
-
-        let java_version = config.get_java_version(minecraft_version.clone()).await.expect("Failed to get Java version");
-        let java_install_path = &config.get_java_install_path().expect("Failed to get Java path from config");
-
-        let java_path = java_install_path.to_string() + &config.get_java_path(os.to_string(), java_version).expect("Failed to get Java path from config");
-
-        println!("Using Java {}", java_version);
-
-        download_java(&client, java_install_path.as_str(), java_path.as_str(), config.get_java_download(java_key, java_version).unwrap().as_str(), &os)
-            .await
-            .expect("Failed to download Java");
+        let minecraft_version = match minecraft_version {
+            Ok(minecraft_version) => minecraft_version,
+            Err(error) => {
+                println!();
+                println!("Error: {}", error);
+                println!();
 
-        println!("Beginning server download...");
+                wait_for_enter("continue");
+                continue
+            }
+        };
 
-        server_object.download(client.clone(), minecraft_version.clone()).await.expect("Failed to download server");
-        server_object.build(java_path.clone(), minecraft_version.clone()).await;
-        
-        accept_eula().await;
+        if let Err(error) = install_server(server_object.deref(), minecraft_version, 3, &config, &os, &http).await {
+            println!();
+            println!("Error: {}", error);
+            println!();
 
-        if server_object.custom_script() {
-            create_args_file(3);
-        } else {
-            create_launch_script(Some(java_path.as_str()), java_version, &os, 3);
+            wait_for_enter("continue");
+            continue
         }
 
-        save_selected_cache(server_object.deref());
+        accept_eula().await;
 
         println!();
         println!("Your server is ready to go!");
@@ -304,6 +263,7 @@ async fn main() {
 
         if yes_or_no() {
             run_launch_file(&os, server_object.deref()).await;
+            wait_for_enter("continue");
         } else {
             goodbye();
             wait_for_enter("exit");
@@ -312,6 +272,124 @@ async fn main() {
     }
 }
 
+/// Looks up the versions of all `downloaders` at once and summarizes them for the menu, in the same order.
+///
+/// A source that takes longer than [`VERSION_TIMEOUT`] is shown as timed out instead of holding up the menu.
+async fn gather_versions(downloaders: &[Box<dyn Installer>], http: &Http) -> Vec<String> {
+    let spinner = ProgressBar::new(downloaders.len() as u64);
+    spinner.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg} ({pos}/{len})").expect("Failed to set spinner style"));
+    spinner.set_message("Gathering server information...");
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let lookups = downloaders.iter().enumerate().map(|(index, downloader)| {
+        let spinner = &spinner;
+
+        async move {
+            let versions = timeout(VERSION_TIMEOUT, downloader.get_versions(http.clone())).await;
+            spinner.inc(1);
+
+            match versions {
+                Ok(versions) => version_summary(versions, index + 1),
+                Err(_) => "timed out".to_string(),
+            }
+        }
+    });
+
+    let summaries = join_all(lookups).await;
+    spinner.finish_and_clear();
+
+    summaries
+}
+
+fn version_summary(versions: Result<Vec<VersionInfo>, DownloadError>, index: usize) -> String {
+    match versions {
+        Ok(versions) => {
+            let mut shown = versions.iter().filter(|version| version.stable).map(|version| version.id.to_string()).collect::<Vec<String>>();
+
+            if shown.is_empty() {
+                shown = versions.iter().map(|version| version.id.to_string()).collect();
+            }
+
+            if shown.len() > 20 {
+                shown = vec![format!("Too many versions, type {}V to list.", index)];
+            }
+
+            shown.reverse();
+            shown.join(", ")
+        }
+        Err(_) => "unavailable".to_string(),
+    }
+}
+
+fn print_versions(name: String, versions: Vec<VersionInfo>, index: usize, show_all: bool) {
+    let mut lines: Vec<(String, Vec<&VersionInfo>)> = vec![];
+
+    for version in versions.iter().filter(|version| version.kind == VersionKind::Release) {
+        let line = MinecraftVersion::parse(&version.id).line().unwrap_or_else(|| version.id.to_string());
+
+        match lines.iter_mut().find(|(existing, _)| existing == &line) {
+            Some((_, line_versions)) => line_versions.push(version),
+            None => lines.push((line, vec![version])),
+        }
+    }
+
+    println!("Versions for {}:", name);
+
+    for (line, line_versions) in lines {
+        let released = line_versions.iter().find_map(|version| version.release_time.as_ref()).map(|time| format!(" (released {})", time.chars().take(10).collect::<String>())).unwrap_or_default();
+        let java = java_range(&line_versions);
+        let ids = line_versions.iter().map(|version| {
+            if version.stable {
+                version.id.to_string()
+            } else {
+                format!("{} (unstable)", version.id)
+            }
+        }).collect::<Vec<String>>();
+
+        println!("{}{}{}: {}", line, released, java, ids.join(", "));
+        println!();
+    }
+
+    let others = [VersionKind::PreRelease, VersionKind::ReleaseCandidate, VersionKind::Snapshot, VersionKind::Experimental];
+    let hidden = versions.iter().filter(|version| version.kind != VersionKind::Release).count();
+
+    if show_all {
+        for kind in others {
+            let ids = versions.iter().filter(|version| version.kind == kind).map(|version| version.id.to_string()).collect::<Vec<String>>();
+
+            if !ids.is_empty() {
+                println!("{}: {}", plural_label(kind), ids.join(", "));
+                println!();
+            }
+        }
+    } else if hidden > 0 {
+        println!("{} snapshots, pre-releases and experimental versions are hidden, type {}VA to list them as well.", hidden, index);
+        println!();
+    }
+}
+
+/// Describes the Java versions `versions` need, e.g. ` (Java 17)` or ` (Java 17-21)`, or nothing if unknown.
+fn java_range(versions: &[&VersionInfo]) -> String {
+    let majors = versions.iter().filter_map(|version| version.java_major);
+
+    match (majors.clone().min(), majors.max()) {
+        (Some(min), Some(max)) if min == max => format!(" (Java {})", min),
+        (Some(min), Some(max)) => format!(" (Java {}-{})", min, max),
+        _ => String::new(),
+    }
+}
+
+/// The heading for a list of versions of `kind`.
+fn plural_label(kind: VersionKind) -> &'static str {
+    match kind {
+        VersionKind::Release => "Releases",
+        VersionKind::Snapshot => "Snapshots",
+        VersionKind::PreRelease => "Pre-releases",
+        VersionKind::ReleaseCandidate => "Release candidates",
+        VersionKind::Experimental => "Experimental versions",
+    }
+}
+
 fn change_ram() {
     print!("Enter the amount of RAM you want to allocate to the server in gigabytes: ");
 
@@ -325,166 +403,81 @@ fn change_ram() {
     let ram = ram_input.parse::<i32>().expect("Failed to parse RAM");
 
     create_args_file(ram);
-}
 
-fn goodbye() {
-    println!("Hava a nice day!");
-    println!("Tool was created by Loudbook, contact me on Discord: @loudbook");
-    println!();
-}
-
-fn yes_or_no() -> bool {
-    let mut input = user_input();
-
-    while input != "y" && input != "n" {
-        print!("Please enter y or n: ");
-        input = user_input();
+    if let Err(error) = update_instance(|instance| instance.ram = Some(ram)) {
+        println!("Error: {}", error);
     }
-
-    input == "y"
 }
 
-fn create_launch_script(java_path: Option<&str>, java_version: i32, os: &OS, ram: i32) {
-    println!("Creating launch script...");
-    create_args_file(ram);
-
-    let file_name = if os == &OS::Windows {
-        "./launch.bat"
-    } else {
-        "./launch.sh"
-    };
+/// Checks whether a newer build of the installed server is available, giving up after [`VERSION_TIMEOUT`].
+///
+/// Failures are not shown, the menu works the same without the check.
+async fn find_update(server_object: &dyn Installer, http: &Http) -> Option<Update> {
+    let instance = Instance::load()?;
 
-    let args_str = if java_version == 8 {
-        let args_vec = fs::read_to_string("./user_jvm_args.txt").expect("Failed to read user_jvm_args.txt");
-        let args_vec = args_vec.split_whitespace().collect::<Vec<&str>>();
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_message("Checking for updates...");
+    spinner.enable_steady_tick(Duration::from_millis(100));
 
-        let mut args = String::new();
+    let update = timeout(VERSION_TIMEOUT, check_for_update(server_object, &instance, http)).await;
+    spinner.finish_and_clear();
 
-        for arg in args_vec {
-            if !arg.starts_with('#') {
-                args += (" ".to_string() + arg).as_str()
-            }
-        }
+    update.ok()?.ok()?
+}
 
-        args
-    } else {
-        "@user_jvm_args.txt".to_string()
+/// Upgrades the installed server to its newest build, asking first if `confirm` is set.
+async fn upgrade(server_object: &dyn Installer, config: &ConfigFile, os: &OS, http: &Http, confirm: bool) {
+    let Some(instance) = Instance::load() else {
+        return;
     };
 
-    match java_path {
-        None => {
-            let original_content = fs::read_to_string(file_name).expect("Failed to read launch file");
-            let original_java_path = original_content.lines()
-                .filter(|line| !line.starts_with('#') && !line.starts_with("REM" ) && !line.starts_with('@'))
-                .collect::<Vec<&str>>().first().unwrap().split_whitespace().collect::<Vec<&str>>()[0];
-
-            fs::remove_file(file_name).expect("Failed to remove launch file");
-
-            let file = File::create(file_name).unwrap();
-            let mut file = BufWriter::new(file);
-
-            if os == &OS::Windows {
-                file.write_all(
-                    format!(
-                        "@echo off\n\"{}\" {} -jar server.jar",
-                        original_java_path.replace('"', ""),
-                        args_str
-                    )
-                        .as_bytes(),
-                ).expect("Failed to write to launch file");
-            } else {
-                file.write_all(
-                    format!(
-                        "#!#!/usr/bin/env sh\n\"{}\" {} -jar server.jar",
-                        original_java_path.replace('"', ""),
-                        args_str
-                    )
-                        .as_bytes(),
-                ).expect("Failed to write to launch file");
-            }
-        }
-        Some(java_path) => {
-            let file = File::create(file_name).unwrap();
-            let mut file = BufWriter::new(file);
-
-            file.write_all(
-                format!(
-                    "\"{}\" {} -jar server.jar",
-                    java_path,
-                    args_str
-                )
-                    .as_bytes(),
-            )
-                .expect("Failed to write to launch file");
+    let plan = match plan_upgrade(server_object, &instance, UpgradeTarget::SameVersion, config, os, http).await {
+        Ok(plan) => plan,
+        Err(error) => {
+            println!("Error: {}", error);
+            return;
         }
-    }
+    };
 
-    if os != &OS::Windows {
-        Command::new("chmod")
-            .arg("+x")
-            .arg("./launch.sh")
-            .output()
-            .expect("Failed to chmod launch.sh");
+    if plan.is_current() {
+        println!("{} is already up to date. To move to another Minecraft version, use the upgrade command with --mc.", plan.install.distribution);
+        return;
     }
 
-    println!("Launch script was created!");
-}
+    if confirm {
+        print!("Upgrade {}? Your server will be backed up first. (y/n): ", plan);
 
-fn create_args_file(ram: i32) {
-    match File::open("./user_jvm_args.txt") {
-        Ok(_) => {
-            let mut file = File::open("user_jvm_args.txt").expect("Failed to open user_jvm_args.txt");
-            let mut content = String::new();
-            file.read_to_string(&mut content).expect("Failed to read user_jvm_args.txt");
-
-            let new_script = if content.contains("-Xmx") {
-                content.lines().collect::<Vec<&str>>().iter().map(|s| {
-                    if s.contains("-Xmx") && !s.starts_with('#') {
-                        format!("-Xmx{}G", ram)
-                    } else {
-                        s.to_string()
-                    }
-                }).collect::<Vec<String>>().join("\n")
-            } else {
-                format!("{} -Xms1024M -Xmx{}G", content, ram)
-            };
-
-            fs::write("user_jvm_args.txt", new_script).expect("Failed to write to user_jvm_args.txt");
+        if !yes_or_no() {
+            return;
         }
-        Err(_) => {
-            let file = File::create("./user_jvm_args.txt").unwrap();
-            let mut file = BufWriter::new(file);
-
-            file.write_all(
-                format!(
-                    "-Xms1024M -Xmx{}G",
-                    ram
-                )
-                    .as_bytes(),
-            )
-                .expect("Failed to write to user_jvm_args.txt");
+    } else {
+        println!("Upgrading {}", plan);
+    }
+
+    match execute_upgrade(server_object, &plan, true, config, os, http).await {
+        Ok(backup) => {
+            println!("The previous server was backed up to {}.", backup.unwrap_or_default());
+            println!("Your server was upgraded!");
         }
-    };
+        Err(error) => println!("Error: {}", error),
+    }
 }
 
-async fn accept_eula() {
-    println!("Checking EULA...");
-    let file = File::create("./eula.txt").unwrap();
-    let mut file = BufWriter::new(file);
-
-    file.write_all("eula=true".as_bytes()).unwrap();
+fn goodbye() {
+    println!("Hava a nice day!");
+    println!("Tool was created by Loudbook, contact me on Discord: @loudbook");
+    println!();
 }
 
-fn extract(file: &File, path: &str, os: &OS) {
-    if os == &OS::Windows {
-        let mut archive = zip::ZipArchive::new(file).expect("Failed to create ZipArchive");
-        archive.extract(path).expect("Failed to extract Java file");
-    } else {
-        let decompressed = GzDecoder::new(file);
+fn yes_or_no() -> bool {
+    let mut input = user_input();
 
-        let mut archive = Archive::new(decompressed);
-        archive.unpack(path).expect("Failed to extract Java file");
+    while input != "y" && input != "n" {
+        print!("Please enter y or n: ");
+        input = user_input();
     }
+
+    input == "y"
 }
 
 fn prepare_hook() {
@@ -502,65 +495,8 @@ fn wait_for_enter(message: &str) {
     let _ = stdin().read_line(&mut String::new());
 }
 
-async fn run_launch_file(os: &OS, server: &dyn Installer) {
-    println!("Starting server...");
-
-    let mut content = String::new();
-
-    if os == &OS::Windows {
-        File::open("./launch.bat").expect("Failed to open launch.bat").read_to_string(&mut content).expect("Failed to read launch.bat");
-    } else {
-        File::open("./launch.sh").expect("Failed to open launch.sh").read_to_string(&mut content).expect("Failed to read launch.sh");
-    };
-
-    let args_no_comments = content.lines().filter(
-        |line| !line.starts_with('#') && !line.starts_with("REM") && !line.starts_with('@')).collect::<Vec<&str>>();
-
-    let java_path = &args_no_comments.clone().first().unwrap().split_whitespace().collect::<Vec<&str>>()[0].replace('"', "");
-    let args = &args_no_comments.first().unwrap().split_whitespace().collect::<Vec<&str>>()[1..];
-
-    println!("Starting server with Java path: {}", java_path);
-
-    let mut process = Command::new(java_path)
-        .args(args.iter().map(|s| s.replace('"', "")).collect::<Vec<String>>())
-        .stdout(Stdio::piped())
-        .spawn().expect("Failed to start server");
-
-    let out = process.stdout.take()
-        .expect("Failed to capture standard output");
-    let reader = BufReader::new(out);
-
-    let mut address: Option<SocketAddrV4> = None;
-    for line in reader.lines() {
-        let line = line;
-
-        if let Ok(line) = line {
-            println!("{}", line);
-
-            if server.startup_message(line.clone()).await.is_some() {
-                address = server.startup_message(line.clone()).await;
-            }
-
-            if line.contains("Done (") || line.contains("Listening on /") {
-                println!();
-                println!("Server is ready!");
-                println!("To safely stop the server, type 'stop' and press enter.");
-
-                if address.is_some() {
-                    println!("If you port forwarded your server, other people can join using the following IP: {}", address.unwrap());
-                }
-
-                println!();
-            }
-        }
-    }
-
-    process.wait().expect("Failed to wait for server to finish");
-    wait_for_enter("continue");
-}
-
 fn change_port(server: &dyn Installer) {
-    if server.get_name() == "Geyser" {
+    if server.get_id() == "geyser" {
         let config = fs::read_to_string("./config.yml").expect("config.yml not found. Make sure you have run the server at least once!");
         let config = config.split('\n').collect::<Vec<&str>>();
 
@@ -599,7 +535,7 @@ fn change_port(server: &dyn Installer) {
         }
 
         return
-    } else if server.get_name() == "BungeeCord" || server.get_name() == "Waterfall" {
+    } else if server.get_id() == "bungeecord" || server.get_id() == "waterfall" {
         let config = fs::read_to_string("./config.yml").expect("config.yml not found. Make sure you have run the server at least once!");
         let config = config.split('\n').collect::<Vec<&str>>();
 
@@ -633,8 +569,12 @@ fn change_port(server: &dyn Installer) {
             file.write_all(line.as_bytes()).expect("Failed to write to config.yml");
         }
 
+        if let Err(error) = update_instance(|instance| instance.port = new_port.parse::<u16>().ok()) {
+            println!("Error: {}", error);
+        }
+
         return
-    } else if server.get_name() == "Velocity" {
+    } else if server.get_id() == "velocity" {
         let toml = fs::read_to_string("./velocity.toml").expect("velocity.toml not found. Make sure you have run the server at least once!");
         let toml = toml.split('\n').collect::<Vec<&str>>();
 
@@ -668,41 +608,28 @@ fn change_port(server: &dyn Installer) {
             file.write_all(line.as_bytes()).expect("Failed to write to velocity.toml");
         }
 
+        if let Err(error) = update_instance(|instance| instance.port = new_port.parse::<u16>().ok()) {
+            println!("Error: {}", error);
+        }
+
         return
     }
 
-    let file = File::open("./server.properties").expect("Failed to open server.properties");
-    let mut file = BufReader::new(file);
-
-    let mut content = String::new();
-    file.read_to_string(&mut content).expect("Failed to read server.properties");
-
-    let lines = content.lines().collect::<Vec<&str>>();
-    let mut new_lines = Vec::new();
+    print!("Enter the new port you want to use: ");
 
-    for line in lines {
-        if line.contains("server-port=") {
-            print!("Enter the new port you want to use: ");
+    let mut new_port = user_input();
 
-            let mut new_port = user_input();
-
-            while new_port.parse::<i32>().is_err() || new_port.parse::<i32>().unwrap() < 1 || new_port.parse::<i32>().unwrap() > 65535 {
-                print!("Please enter a valid port: ");
-                new_port = user_input();
-            }
-
-            new_lines.push(line.split('=').collect::<Vec<&str>>()[0].to_string() + format!("={}", new_port).as_str());
-        } else {
-            new_lines.push(line.to_string());
-        }
+    while new_port.parse::<u16>().is_err() || new_port.parse::<u16>().unwrap() < 1 {
+        print!("Please enter a valid port: ");
+        new_port = user_input();
     }
 
-    let file = File::create("./server.properties").expect("Failed to create server.properties");
-    let mut file = BufWriter::new(file);
+    let port = new_port.parse::<u16>().expect("Failed to parse port");
+
+    set_server_port(port);
 
-    for mut line in new_lines {
-        line += "\n";
-        file.write_all(line.as_bytes()).expect("Failed to write to server.properties");
+    if let Err(error) = update_instance(|instance| instance.port = Some(port)) {
+        println!("Error: {}", error);
     }
 
     println!("Port was changed!");
@@ -724,30 +651,3 @@ fn user_input() -> String {
 
     input
 }
-
-fn save_selected_cache(server: &dyn Installer) {
-    let file = File::create("./selected_cache.txt").expect("Failed to create selected_cache.txt");
-    let mut file = BufWriter::new(file);
-
-    file.write_all(server.get_name().as_bytes()).expect("Failed to write to selected_cache.txt");
-}
-
-fn get_selected_from_cache(options: &Vec<Box<dyn Installer>>) -> Option<&dyn Installer> {
-    let file = File::open("./selected_cache.txt");
-
-    if file.is_err() {
-        return None
-    }
-
-    let mut file = BufReader::new(file.unwrap());
-    let mut content = String::new();
-    file.read_to_string(&mut content).expect("Failed to read selected_cache.txt");
-
-    for installer in options {
-        if installer.get_name() == content {
-            return Some(installer.deref())
-        }
-    }
-
-    None
-}
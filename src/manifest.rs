@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::backup::remove_entry;
+use crate::checksum::{file_checksum, Checksum, ChecksumAlgorithm};
+use crate::config::ConfigFile;
+use crate::downloader::{download_file, Installer};
+use crate::downloaderror::DownloadError;
+use crate::http::Http;
+use crate::install::{execute_plan, plan_install, resolve_version};
+use crate::instance::Instance;
+use crate::launch::{accept_eula, create_launch_script, write_args_file};
+use crate::lockfile::Lockfile;
+use crate::os::OS;
+use crate::properties::set_server_properties;
+use crate::servertype::ServerType;
+use crate::upgrade::{execute_upgrade, plan_upgrade, UpgradeTarget};
+
+/// The file describing a whole server, meant to be checked in next to it.
+pub const MANIFEST_FILE: &str = "msi-server.toml";
+
+/// A server as described by [`MANIFEST_FILE`], which [`Manifest::apply`] makes the current directory match.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The [`Installer::get_id`] or name of the distribution.
+    pub distribution: String,
+    /// The Minecraft version to install. Without one, the latest version is installed once and then kept.
+    pub minecraft_version: Option<String>,
+    /// The RAM given to the server, in gigabytes.
+    #[serde(default = "default_ram")]
+    pub ram: i32,
+    /// Flags added to `user_jvm_args.txt` after the heap settings.
+    #[serde(default)]
+    pub jvm_flags: Vec<String>,
+    pub port: Option<u16>,
+    /// Values to set in `server.properties`.
+    #[serde(default)]
+    pub properties: BTreeMap<String, toml::Value>,
+    /// Plugins or mods to download into [`Installer::addon_directory`].
+    #[serde(default)]
+    pub addons: Vec<Addon>,
+    #[serde(default)]
+    pub accept_eula: bool,
+    #[serde(skip)]
+    path: String,
+}
+
+/// Removes the server files recorded in [`LOCK_FILE`](crate::lockfile::LOCK_FILE), so switching distributions leaves none of the old one behind.
+///
+/// Worlds, configs and everything else not created by the install are kept. Without a lockfile, nothing is removed.
+fn remove_locked_files() -> Result<(), DownloadError> {
+    let Ok(lockfile) = Lockfile::load() else {
+        return Ok(());
+    };
+
+    for file in lockfile.server.files.iter().chain([&lockfile.server.artifact.path]) {
+        remove_entry(file.trim_start_matches("./"))?;
+    }
+
+    Ok(())
+}
+
+/// A plugin or mod to download.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Addon {
+    pub url: String,
+    /// The file name to save it as. Defaults to the last part of the URL.
+    pub file: Option<String>,
+    /// The expected SHA-256. Without one, an existing file is never downloaded again.
+    pub sha256: Option<String>,
+}
+
+fn default_ram() -> i32 {
+    3
+}
+
+impl Addon {
+    fn file_name(&self) -> Option<String> {
+        self.file.clone().or_else(|| {
+            let url = self.url.split(['?', '#']).next()?;
+            url.rsplit('/').next().filter(|name| !name.is_empty()).map(|name| name.to_string())
+        })
+    }
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`.
+    pub fn load(path: &str) -> Result<Manifest, DownloadError> {
+        let content = fs::read_to_string(path).map_err(|error| DownloadError::io(path, error))?;
+        let manifest = toml::from_str::<Manifest>(&content).map_err(|error| DownloadError::invalid_file(path, &error.to_string()))?;
+
+        Ok(Manifest { path: path.to_string(), ..manifest })
+    }
+
+    /// Makes the current directory match the manifest and returns what was changed.
+    ///
+    /// The server is installed when nothing is installed. A different Minecraft version is installed as an upgrade, see
+    /// [`execute_upgrade`], so the old one is backed up and can be rolled back. A different distribution replaces the
+    /// files recorded in the old one's [`LOCK_FILE`](crate::lockfile::LOCK_FILE). After that,
+    /// `user_jvm_args.txt`, the launch script, `server.properties`, addons and the EULA are only written where they
+    /// differ, so applying the same manifest again changes nothing. Addons that are no longer listed are left alone.
+    pub async fn apply(&self, server_object: &dyn Installer, config: &ConfigFile, os: &OS, http: &Http) -> Result<Vec<String>, DownloadError> {
+        self.validate(server_object)?;
+
+        let mut changes = Vec::new();
+        let minecraft_version = resolve_version(server_object, self.minecraft_version.clone().filter(|version| version != "latest"), http).await?;
+
+        let existing = Instance::load();
+        let installed = existing
+            .clone()
+            .filter(|instance| instance.distribution == server_object.get_id())
+            .filter(|instance| minecraft_version.is_none() || instance.minecraft_version == minecraft_version)
+            .and_then(|instance| Some((instance.java_path?, instance.java_major?)));
+
+        let (java_path, java_major) = match (installed, existing) {
+            (Some(installed), _) => installed,
+            (None, Some(instance)) if instance.distribution == server_object.get_id() => {
+                let target = minecraft_version.map(UpgradeTarget::Version).unwrap_or(UpgradeTarget::Latest);
+                let mut plan = plan_upgrade(server_object, &instance, target, config, os, http).await?;
+                plan.install.ram = self.ram;
+
+                let backup = execute_upgrade(server_object, &plan, true, config, os, http).await?;
+                changes.push(format!("Upgraded {}, the previous server was backed up to {}", plan, backup.unwrap_or_default()));
+
+                (plan.install.java.executable, plan.install.java.major)
+            }
+            (None, existing) => {
+                if let Some(instance) = existing {
+                    remove_locked_files()?;
+                    changes.push(format!("Removed {}", instance.distribution));
+                }
+
+                let plan = plan_install(server_object, minecraft_version, self.ram, config, os, http).await?;
+                execute_plan(server_object, &plan, os, http).await?;
+
+                let version = plan.server.minecraft_version.as_ref().or(plan.server.version.as_ref());
+                changes.push(format!("Installed {} {}", plan.distribution, version.map(|version| version.as_str()).unwrap_or("latest")));
+
+                (plan.java.executable, plan.java.major)
+            }
+        };
+
+        if write_args_file(self.ram, &self.jvm_flags) {
+            changes.push("Updated user_jvm_args.txt".to_string());
+
+            if !server_object.custom_script() {
+                create_launch_script(Some(java_path.as_str()), java_major, os, self.ram);
+            }
+        }
+
+        if let Some(instance) = Instance::load().filter(|instance| instance.ram != Some(self.ram) || (self.port.is_some() && instance.port != self.port)) {
+            Instance { ram: Some(self.ram), port: self.port.or(instance.port), ..instance }.save()?;
+        }
+
+        let mut properties = self.properties
+            .iter()
+            .map(|(key, value)| (key.clone(), value.as_str().map(|value| value.to_string()).unwrap_or(value.to_string())))
+            .collect::<BTreeMap<String, String>>();
+
+        if let Some(port) = self.port {
+            properties.insert("server-port".to_string(), port.to_string());
+        }
+
+        if !properties.is_empty() {
+            for key in set_server_properties(&properties) {
+                changes.push(format!("Set {} in server.properties", key));
+            }
+        }
+
+        if let Some(directory) = server_object.addon_directory().filter(|_| !self.addons.is_empty()) {
+            fs::create_dir_all(&directory).map_err(|error| DownloadError::io(&directory, error))?;
+
+            for addon in &self.addons {
+                let path = format!("{}/{}", directory, addon.file_name().unwrap_or_default());
+                let checksum = addon.sha256.as_ref().map(|sha256| Checksum::new(ChecksumAlgorithm::Sha256, sha256));
+                let current = Path::new(&path).exists() && checksum.as_ref().is_none_or(|checksum| {
+                    file_checksum(&path, ChecksumAlgorithm::Sha256).is_ok_and(|actual| &actual == checksum)
+                });
+
+                if !current {
+                    download_file(http, &addon.url, &path, checksum.as_ref()).await?;
+                    changes.push(format!("Downloaded {}", path));
+                }
+            }
+        }
+
+        if self.accept_eula && !fs::read_to_string("./eula.txt").is_ok_and(|content| content.contains("eula=true")) {
+            accept_eula().await;
+            changes.push("Accepted the Minecraft EULA".to_string());
+        }
+
+        Ok(changes)
+    }
+
+    /// Checks that everything in the manifest is supported by `server_object`, before anything is changed.
+    fn validate(&self, server_object: &dyn Installer) -> Result<(), DownloadError> {
+        if self.ram < 1 {
+            return Err(DownloadError::invalid_file(&self.path, "ram has to be at least 1"));
+        }
+
+        if (self.port.is_some() || !self.properties.is_empty()) && server_object.get_type() != ServerType::Server {
+            return Err(DownloadError::invalid_file(&self.path, &format!("port and properties are only supported for servers, not {}", server_object.get_name())));
+        }
+
+        if !self.addons.is_empty() && server_object.addon_directory().is_none() {
+            return Err(DownloadError::invalid_file(&self.path, &format!("{} does not support plugins or mods", server_object.get_name())));
+        }
+
+        if let Some(addon) = self.addons.iter().find(|addon| addon.file_name().is_none()) {
+            return Err(DownloadError::invalid_file(&self.path, &format!("set file for the addon at {}", addon.url)));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,380 @@
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::versioninfo::VersionKind;
+
+/// The first snapshot week of each release cycle, used to order snapshots against releases.
+///
+/// A snapshot belongs to the cycle with the latest start at or before it. Snapshots newer than the
+/// last entry are treated as belonging to the release after the newest one listed here.
+const SNAPSHOT_CYCLES: &[(u32, u32, &str)] = &[
+    (13, 1, "1.5"),
+    (13, 16, "1.6"),
+    (13, 36, "1.7"),
+    (13, 47, "1.7.4"),
+    (14, 2, "1.8"),
+    (15, 31, "1.9"),
+    (16, 14, "1.9.3"),
+    (16, 20, "1.10"),
+    (16, 32, "1.11"),
+    (16, 50, "1.11.1"),
+    (17, 6, "1.12"),
+    (17, 43, "1.13"),
+    (18, 30, "1.13.1"),
+    (18, 43, "1.14"),
+    (19, 34, "1.15"),
+    (20, 6, "1.16"),
+    (20, 27, "1.16.2"),
+    (20, 45, "1.17"),
+    (21, 37, "1.18"),
+    (22, 3, "1.18.2"),
+    (22, 11, "1.19"),
+    (22, 24, "1.19.1"),
+    (22, 42, "1.19.3"),
+    (23, 3, "1.19.4"),
+    (23, 12, "1.20"),
+    (23, 31, "1.20.2"),
+    (23, 40, "1.20.3"),
+    (23, 51, "1.20.5"),
+    (24, 18, "1.21"),
+    (24, 33, "1.21.2"),
+    (24, 44, "1.21.4"),
+    (25, 2, "1.21.5"),
+    (25, 15, "1.21.6"),
+    (25, 31, "1.21.9"),
+    (25, 41, "1.21.11"),
+];
+
+/// Suffixes that mark a version as leading up to a release, followed by the stage number.
+const STAGE_SUFFIXES: &[StageSuffix] = &[
+    ("-snapshot-", Stage::Snapshot),
+    ("-pre", Stage::PreRelease),
+    ("_pre", Stage::PreRelease),
+    (" Pre-Release ", Stage::PreRelease),
+    ("-rc", Stage::ReleaseCandidate),
+    (" Release Candidate ", Stage::ReleaseCandidate),
+];
+
+type StageSuffix = (&'static str, fn(u32) -> Stage);
+
+/// A Minecraft version such as `1.20.4`, `1.18-pre2`, `1.20.5-rc1`, `26.1-snapshot-1` or `24w14a`.
+///
+/// Versions are ordered by release, so `24w14a` sorts after `1.20.4` and before `1.20.5-pre1`.
+/// Trailing zeros are ignored, which makes `1.21` and `1.21.0` equal. IDs that do not follow any
+/// of these formats, like old alphas and April Fools versions, sort before everything else.
+#[derive(Clone, Debug)]
+pub struct MinecraftVersion {
+    id: String,
+    parsed: Parsed,
+}
+
+#[derive(Clone, Debug)]
+enum Parsed {
+    Release { numbers: Vec<u32>, stage: Stage },
+    Snapshot { year: u32, week: u32, letter: char },
+    Other,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    Snapshot(u32),
+    PreRelease(u32),
+    ReleaseCandidate(u32),
+    Release,
+}
+
+impl MinecraftVersion {
+    pub fn parse(id: &str) -> MinecraftVersion {
+        let id = id.trim();
+
+        MinecraftVersion {
+            id: id.to_string(),
+            parsed: parse_release(id).or_else(|| parse_snapshot(id)).unwrap_or(Parsed::Other),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn kind(&self) -> VersionKind {
+        match &self.parsed {
+            Parsed::Release { stage: Stage::Release, .. } => VersionKind::Release,
+            Parsed::Release { stage: Stage::ReleaseCandidate(_), .. } => VersionKind::ReleaseCandidate,
+            Parsed::Release { stage: Stage::PreRelease(_), .. } => VersionKind::PreRelease,
+            Parsed::Release { stage: Stage::Snapshot(_), .. } | Parsed::Snapshot { .. } => VersionKind::Snapshot,
+            Parsed::Other => VersionKind::Experimental,
+        }
+    }
+
+    /// The release line this version belongs to, e.g. `1.20` for `1.20.4` and `1.20.5-rc1`, or `None` for snapshots
+    /// and unknown IDs.
+    pub fn line(&self) -> Option<String> {
+        match &self.parsed {
+            Parsed::Release { numbers, .. } => Some(format!("{}.{}", numbers[0], numbers[1])),
+            _ => None,
+        }
+    }
+
+    pub fn is_release(&self) -> bool {
+        self.kind() == VersionKind::Release
+    }
+
+    /// Whether this version belongs to the release line `line`, e.g. `1.20.4` and `1.20.5-rc1` are both in `1.20`.
+    ///
+    /// A line always contains itself, so a snapshot is only in its own line.
+    pub fn is_in_line(&self, line: &MinecraftVersion) -> bool {
+        match (&self.parsed, &line.parsed) {
+            (Parsed::Release { numbers, .. }, Parsed::Release { numbers: line_numbers, stage: Stage::Release }) => {
+                let line_numbers = trim_zeros(line_numbers);
+                numbers.len() >= line_numbers.len() && numbers[..line_numbers.len()] == *line_numbers
+            }
+            _ => self == line,
+        }
+    }
+
+    /// Picks the version `requested` refers to from `available`.
+    ///
+    /// An exact match wins. Otherwise `requested` is treated as a release line and its newest
+    /// release is returned, falling back to its newest pre-release or release candidate.
+    pub fn resolve<'a>(requested: &str, available: &'a [String]) -> Option<&'a String> {
+        let requested = MinecraftVersion::parse(requested);
+        let parsed = available.iter().map(|id| (id, MinecraftVersion::parse(id))).collect::<Vec<(&String, MinecraftVersion)>>();
+
+        if let Some((id, _)) = parsed.iter().find(|(id, version)| id.as_str() == requested.id() || version == &requested) {
+            return Some(id);
+        }
+
+        let in_line = parsed.iter().filter(|(_, version)| version.is_in_line(&requested)).collect::<Vec<&(&String, MinecraftVersion)>>();
+
+        in_line.iter()
+            .filter(|(_, version)| version.is_release())
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .or_else(|| in_line.iter().max_by(|(_, a), (_, b)| a.cmp(b)))
+            .map(|(id, _)| *id)
+    }
+
+    /// Returns the newest of `versions`.
+    pub fn latest<'a>(versions: impl IntoIterator<Item = &'a String>) -> Option<&'a String> {
+        versions.into_iter().max_by_key(|id| MinecraftVersion::parse(id))
+    }
+
+    fn sort_key(&self) -> (Vec<u32>, Stage) {
+        match &self.parsed {
+            Parsed::Release { numbers, stage } => (trim_zeros(numbers).to_vec(), *stage),
+            Parsed::Snapshot { year, week, .. } => {
+                let cycle = SNAPSHOT_CYCLES.iter().rev().find(|(start_year, start_week, _)| (start_year, start_week) <= (year, week));
+
+                match cycle {
+                    Some((_, _, release)) if cycle != SNAPSHOT_CYCLES.last() => (release_numbers(release), Stage::Snapshot(0)),
+                    _ => {
+                        let mut numbers = release_numbers(SNAPSHOT_CYCLES.last().unwrap().2);
+                        numbers.push(0);
+                        numbers.push(1); // Sorts after the last known release and before the one following it.
+
+                        (numbers, Stage::Snapshot(0))
+                    }
+                }
+            }
+            Parsed::Other => (vec![], Stage::Release),
+        }
+    }
+}
+
+fn parse_release(id: &str) -> Option<Parsed> {
+    let (numbers, stage) = STAGE_SUFFIXES
+        .iter()
+        .find_map(|(suffix, stage)| {
+            let (numbers, number) = id.split_once(suffix)?;
+            Some((numbers, stage(number.parse::<u32>().ok()?)))
+        })
+        .unwrap_or((id, Stage::Release));
+
+    let numbers = numbers.split('.').map(|part| part.parse::<u32>().ok()).collect::<Option<Vec<u32>>>()?;
+
+    if numbers.len() < 2 {
+        return None;
+    }
+
+    Some(Parsed::Release { numbers, stage })
+}
+
+fn parse_snapshot(id: &str) -> Option<Parsed> {
+    let (year, rest) = id.split_once('w')?;
+
+    if year.len() != 2 || rest.len() != 3 {
+        return None;
+    }
+
+    let letter = rest.chars().last()?;
+
+    if !letter.is_ascii_lowercase() {
+        return None;
+    }
+
+    Some(Parsed::Snapshot {
+        year: year.parse().ok()?,
+        week: rest[..2].parse().ok()?,
+        letter,
+    })
+}
+
+/// Drops trailing zeros after the second number, so `1.20.0` becomes `1.20` but `1.0` stays `1.0`.
+fn trim_zeros(numbers: &[u32]) -> &[u32] {
+    let length = numbers.iter().rposition(|number| *number != 0).map(|index| index + 1).unwrap_or(0);
+    &numbers[..length.max(2).min(numbers.len())]
+}
+
+fn release_numbers(release: &str) -> Vec<u32> {
+    release.split('.').filter_map(|part| part.parse().ok()).collect()
+}
+
+impl Ord for MinecraftVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.parsed, &other.parsed) {
+            (Parsed::Other, Parsed::Other) => self.id.cmp(&other.id),
+            (Parsed::Other, _) => Ordering::Less,
+            (_, Parsed::Other) => Ordering::Greater,
+            (Parsed::Snapshot { year, week, letter }, Parsed::Snapshot { year: other_year, week: other_week, letter: other_letter }) => {
+                (year, week, letter).cmp(&(other_year, other_week, other_letter))
+            }
+            _ => self.sort_key().cmp(&other.sort_key()),
+        }
+    }
+}
+
+impl PartialOrd for MinecraftVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MinecraftVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MinecraftVersion {}
+
+impl FromStr for MinecraftVersion {
+    type Err = Infallible;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Ok(MinecraftVersion::parse(id))
+    }
+}
+
+impl Display for MinecraftVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(id: &str) -> MinecraftVersion {
+        MinecraftVersion::parse(id)
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn stages_sort_before_their_release() {
+        let ordered = ["1.20.4", "1.20.5-pre1", "1.20.5-pre2", "1.20.5-rc1", "1.20.5", "1.20.6-rc1", "1.20.6"];
+
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{} should sort before {}", pair[0], pair[1]);
+        }
+
+        assert_eq!(version("1.18-pre2").kind(), VersionKind::PreRelease);
+        assert_eq!(version("1.20.5-rc1").kind(), VersionKind::ReleaseCandidate);
+        assert_eq!(version("1.20.5").kind(), VersionKind::Release);
+    }
+
+    #[test]
+    fn snapshots_sort_between_releases() {
+        assert_eq!(version("24w14a").kind(), VersionKind::Snapshot);
+        assert!(version("1.20.4") < version("24w14a"));
+        assert!(version("24w14a") < version("1.20.5-pre1"));
+        assert!(version("24w13a") < version("24w14a"));
+        assert!(version("24w14a") < version("24w14b"));
+        assert!(version("23w51a") > version("1.20.4"));
+        assert!(version("23w45a") < version("1.20.3"));
+        assert!(version("1.20.2") < version("23w45a"));
+    }
+
+    #[test]
+    fn snapshots_after_the_known_cycles_sort_after_the_newest_release() {
+        assert!(version("30w01a") > version("1.21.11"));
+        assert!(version("30w01a") < version("1.21.12"));
+    }
+
+    #[test]
+    fn trailing_zeros_are_ignored() {
+        assert_eq!(version("1.20"), version("1.20.0"));
+        assert_eq!(version("1.20").cmp(&version("1.20.0")), Ordering::Equal);
+        assert!(version("1.20") < version("1.20.1"));
+    }
+
+    #[test]
+    fn resolve_picks_the_newest_release_of_a_line() {
+        let available = ids(&["1.2.5", "1.20.1", "1.20.4", "1.20.5-pre1", "1.21"]);
+
+        assert_eq!(MinecraftVersion::resolve("1.20", &available).map(|id| id.as_str()), Some("1.20.4"));
+        assert_eq!(MinecraftVersion::resolve("1.2", &available).map(|id| id.as_str()), Some("1.2.5"));
+        assert_eq!(MinecraftVersion::resolve("1.21.0", &available).map(|id| id.as_str()), Some("1.21"));
+        assert_eq!(MinecraftVersion::resolve("1.19", &available), None);
+    }
+
+    #[test]
+    fn resolve_falls_back_to_the_newest_stage() {
+        let available = ids(&["1.20.4", "1.20.5-pre1", "1.20.5-rc1"]);
+
+        assert_eq!(MinecraftVersion::resolve("1.20.5", &available).map(|id| id.as_str()), Some("1.20.5-rc1"));
+    }
+
+    #[test]
+    fn resolve_does_not_treat_1_0_as_every_1_x() {
+        let available = ids(&["1.8.8", "1.20.4"]);
+
+        assert_eq!(MinecraftVersion::resolve("1.0", &available), None);
+        assert!(!version("1.20.4").is_in_line(&version("1.0")));
+        assert!(version("1.0.0").is_in_line(&version("1.0")));
+    }
+
+    #[test]
+    fn lines_only_match_whole_numbers() {
+        assert!(version("1.20.4").is_in_line(&version("1.20")));
+        assert!(version("1.20.5-rc1").is_in_line(&version("1.20")));
+        assert!(version("1.20").is_in_line(&version("1.20.0")));
+        assert!(!version("1.201").is_in_line(&version("1.20")));
+        assert!(!version("1.2.5").is_in_line(&version("1.20")));
+        assert!(!version("24w14a").is_in_line(&version("1.20")));
+    }
+
+    #[test]
+    fn releases_and_their_stages_share_a_line() {
+        assert_eq!(version("1.20.4").line().as_deref(), Some("1.20"));
+        assert_eq!(version("1.20.5-rc1").line().as_deref(), Some("1.20"));
+        assert_eq!(version("1.8").line().as_deref(), Some("1.8"));
+        assert_eq!(version("24w14a").line(), None);
+    }
+
+    #[test]
+    fn unknown_ids_are_experimental() {
+        for id in ["", "w", "24w1", "24wé1", "1.20-foo", "Combat Test 8c", "3D Shareware v1.34", "b1.7.3", "1.20.5-rc", "1.é"] {
+            let parsed = version(id);
+
+            assert_eq!(parsed.kind(), VersionKind::Experimental, "{}", id);
+            assert!(parsed < version("1.0"), "{}", id);
+        }
+
+        assert!(version("a") < version("b"));
+    }
+}
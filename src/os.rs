@@ -1,13 +1,40 @@
+use std::env;
 use std::fmt::{Display, Formatter};
 
 #[allow(clippy::enum_variant_names)]
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum OS {
     Windows,
     Linux,
     MacOS,
 }
 
+impl OS {
+    /// Returns the OS this binary was built for.
+    pub fn current() -> OS {
+        if cfg!(target_os = "macos") {
+            OS::MacOS
+        } else if cfg!(target_os = "linux") {
+            OS::Linux
+        } else if cfg!(target_os = "windows") {
+            OS::Windows
+        } else {
+            panic!("Unsupported OS.");
+        }
+    }
+
+    /// Returns the prefix of the `java_downloads` config keys for this OS and the current CPU architecture.
+    pub fn java_key(&self) -> String {
+        let is_arm = env::consts::ARCH.contains("arch64") || env::consts::ARCH.contains("arm");
+
+        if is_arm {
+            self.to_string() + "_arm"
+        } else {
+            self.to_string()
+        }
+    }
+}
+
 impl Display for OS {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let str = match self {
@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Sets `server-port` in `server.properties`, creating the file if the server has not been started yet.
+pub fn set_server_port(port: u16) {
+    set_server_properties(&BTreeMap::from([("server-port".to_string(), port.to_string())]));
+}
+
+/// Sets each of `properties` in `server.properties`, creating the file if the server has not been started yet.
+///
+/// Returns the keys whose value changed. The file is only rewritten if there are any.
+pub fn set_server_properties(properties: &BTreeMap<String, String>) -> Vec<String> {
+    let content = fs::read_to_string("./server.properties").unwrap_or_default();
+
+    let mut changed = Vec::new();
+    let mut found = Vec::new();
+    let mut new_lines = Vec::new();
+
+    for line in content.lines() {
+        let key = line.split_once('=').map(|(key, _)| key.trim()).filter(|_| !line.starts_with('#'));
+
+        match key.and_then(|key| properties.get_key_value(key)) {
+            Some((key, value)) => {
+                let new_line = format!("{}={}", key, value);
+
+                if new_line != line {
+                    changed.push(key.clone());
+                }
+
+                found.push(key.clone());
+                new_lines.push(new_line);
+            }
+            None => new_lines.push(line.to_string()),
+        }
+    }
+
+    for (key, value) in properties {
+        if !found.contains(key) {
+            changed.push(key.clone());
+            new_lines.push(format!("{}={}", key, value));
+        }
+    }
+
+    if changed.is_empty() {
+        return changed;
+    }
+
+    let file = File::create("./server.properties").expect("Failed to create server.properties");
+    let mut file = BufWriter::new(file);
+
+    for mut line in new_lines {
+        line += "\n";
+        file.write_all(line.as_bytes()).expect("Failed to write to server.properties");
+    }
+
+    changed
+}
@@ -0,0 +1,254 @@
+use std::{env, fs};
+use std::path::{absolute, Path};
+use crate::downloaderror::DownloadError;
+use crate::os::OS;
+
+/// Named server directories kept in the data directory, so servers do not have to live next to the installer.
+///
+/// Every operation works on the current directory, so targeting an instance means entering its directory with
+/// [`Registry::enter`] first. An instance can be selected to be entered when no other one is given.
+pub struct Registry {
+    path: String,
+}
+
+impl Registry {
+    /// Uses the registry in the directory at `path`.
+    pub fn new(path: String) -> Registry {
+        Registry { path }
+    }
+
+    /// Opens the registry in the default data directory for `os`.
+    pub fn load(os: &OS) -> Registry {
+        let path = if os == &OS::Windows {
+            env::var("APPDATA").unwrap_or("./".to_string()) + "\\MinecraftServerInstaller"
+        } else if os == &OS::Linux {
+            env::var("XDG_DATA_HOME")
+                .or_else(|_| env::var("HOME").map(|home| home + "/.local/share"))
+                .unwrap_or("./".to_string()) + "/MinecraftServerInstaller"
+        } else {
+            env::var("HOME").unwrap_or("./".to_string()) + "/Library/Application Support/MinecraftServerInstaller"
+        };
+
+        let path = absolute(&path).map(|path| path.to_string_lossy().to_string()).unwrap_or(path);
+
+        Registry::new(path)
+    }
+
+    /// The directory of the instance called `name`, whether it exists or not.
+    pub fn instance_path(&self, name: &str) -> String {
+        format!("{}/instances/{}", self.path, name)
+    }
+
+    /// Returns the names of all instances, sorted.
+    pub fn list(&self) -> Vec<String> {
+        let mut names = fs::read_dir(format!("{}/instances", self.path))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+
+        names.sort();
+        names
+    }
+
+    /// Creates an empty instance called `name` and returns its directory.
+    pub fn create(&self, name: &str) -> Result<String, DownloadError> {
+        check_name(name)?;
+
+        let path = self.instance_path(name);
+
+        if Path::new(&path).exists() {
+            return Err(DownloadError::InstanceExists { name: name.to_string() });
+        }
+
+        fs::create_dir_all(&path).map_err(|error| DownloadError::io(&path, error))?;
+
+        Ok(path)
+    }
+
+    /// Deletes the instance called `name` with everything in it, unselecting it if needed.
+    pub fn delete(&self, name: &str) -> Result<(), DownloadError> {
+        let path = self.existing_path(name)?;
+
+        fs::remove_dir_all(&path).map_err(|error| DownloadError::io(&path, error))?;
+
+        if self.selected().as_deref() == Some(name) {
+            self.select(None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Renames the instance `name` to `new_name`, keeping it selected if it was.
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<(), DownloadError> {
+        check_name(new_name)?;
+
+        let path = self.existing_path(name)?;
+        let new_path = self.instance_path(new_name);
+
+        if Path::new(&new_path).exists() {
+            return Err(DownloadError::InstanceExists { name: new_name.to_string() });
+        }
+
+        fs::rename(&path, &new_path).map_err(|error| DownloadError::io(&path, error))?;
+
+        if self.selected().as_deref() == Some(name) {
+            self.select(Some(new_name))?;
+        }
+
+        Ok(())
+    }
+
+    /// The instance used when none is given, if one was selected.
+    pub fn selected(&self) -> Option<String> {
+        fs::read_to_string(self.selected_file())
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Selects the instance called `name`, or the current directory again if `None`.
+    pub fn select(&self, name: Option<&str>) -> Result<(), DownloadError> {
+        let selected_file = self.selected_file();
+
+        match name {
+            Some(name) => {
+                self.existing_path(name)?;
+                fs::write(&selected_file, name).map_err(|error| DownloadError::io(&selected_file, error))
+            }
+            None if Path::new(&selected_file).exists() => fs::remove_file(&selected_file).map_err(|error| DownloadError::io(&selected_file, error)),
+            None => Ok(()),
+        }
+    }
+
+    /// Changes into the directory of the instance `name`, or of the selected instance if `None`.
+    ///
+    /// Stays in the current directory if no instance is given or selected.
+    pub fn enter(&self, name: Option<String>) -> Result<(), DownloadError> {
+        let Some(name) = name.or_else(|| self.selected()) else {
+            return Ok(());
+        };
+
+        let path = self.existing_path(&name)?;
+
+        env::set_current_dir(&path).map_err(|error| DownloadError::io(&path, error))
+    }
+
+    fn existing_path(&self, name: &str) -> Result<String, DownloadError> {
+        let path = self.instance_path(name);
+
+        if check_name(name).is_err() || !Path::new(&path).is_dir() {
+            return Err(DownloadError::InstanceNotFound { name: name.to_string() });
+        }
+
+        Ok(path)
+    }
+
+    fn selected_file(&self) -> String {
+        format!("{}/selected_instance.txt", self.path)
+    }
+}
+
+/// Instance names become directory names, so only letters, digits, `-`, `_` and `.` are allowed, not starting with `.`.
+fn check_name(name: &str) -> Result<(), DownloadError> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|char| char.is_ascii_alphanumeric() || matches!(char, '-' | '_' | '.'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(DownloadError::InvalidInstanceName { name: name.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn registry(directory: &TempDir) -> Registry {
+        Registry::new(directory.path().to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn created_instances_are_listed() {
+        let directory = TempDir::new().unwrap();
+        let registry = registry(&directory);
+
+        let path = registry.create("survival").unwrap();
+        registry.create("creative").unwrap();
+
+        assert!(Path::new(&path).is_dir());
+        assert_eq!(registry.list(), vec!["creative", "survival"]);
+        assert!(matches!(registry.create("survival"), Err(DownloadError::InstanceExists { .. })));
+        assert!(matches!(registry.create("../escape"), Err(DownloadError::InvalidInstanceName { .. })));
+        assert!(matches!(registry.create(".hidden"), Err(DownloadError::InvalidInstanceName { .. })));
+    }
+
+    #[test]
+    fn renaming_keeps_the_selection() {
+        let directory = TempDir::new().unwrap();
+        let registry = registry(&directory);
+        registry.create("survival").unwrap();
+        registry.create("creative").unwrap();
+        registry.select(Some("survival")).unwrap();
+
+        registry.rename("survival", "hardcore").unwrap();
+
+        assert_eq!(registry.list(), vec!["creative", "hardcore"]);
+        assert_eq!(registry.selected().as_deref(), Some("hardcore"));
+        assert!(matches!(registry.rename("hardcore", "creative"), Err(DownloadError::InstanceExists { .. })));
+        assert!(matches!(registry.rename("unknown", "other"), Err(DownloadError::InstanceNotFound { .. })));
+    }
+
+    #[test]
+    fn only_existing_instances_can_be_selected() {
+        let directory = TempDir::new().unwrap();
+        let registry = registry(&directory);
+        registry.create("survival").unwrap();
+
+        assert_eq!(registry.selected(), None);
+        assert!(matches!(registry.select(Some("unknown")), Err(DownloadError::InstanceNotFound { .. })));
+
+        registry.select(Some("survival")).unwrap();
+        assert_eq!(registry.selected().as_deref(), Some("survival"));
+
+        registry.select(None).unwrap();
+        assert_eq!(registry.selected(), None);
+    }
+
+    #[test]
+    fn deleting_the_selected_instance_unselects_it() {
+        let directory = TempDir::new().unwrap();
+        let registry = registry(&directory);
+        let path = registry.create("survival").unwrap();
+        registry.create("creative").unwrap();
+        fs::write(format!("{}/server.jar", path), "jar").unwrap();
+        registry.select(Some("survival")).unwrap();
+
+        registry.delete("survival").unwrap();
+
+        assert!(!Path::new(&path).exists());
+        assert_eq!(registry.list(), vec!["creative"]);
+        assert_eq!(registry.selected(), None);
+        assert!(matches!(registry.delete("survival"), Err(DownloadError::InstanceNotFound { .. })));
+    }
+
+    #[test]
+    fn deleting_another_instance_keeps_the_selection() {
+        let directory = TempDir::new().unwrap();
+        let registry = registry(&directory);
+        registry.create("survival").unwrap();
+        registry.create("creative").unwrap();
+        registry.select(Some("survival")).unwrap();
+
+        registry.delete("creative").unwrap();
+
+        assert_eq!(registry.selected().as_deref(), Some("survival"));
+    }
+}
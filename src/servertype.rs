@@ -1,5 +1,6 @@
+/// Whether an installer sets up a game server or a proxy in front of game servers.
 #[derive(Eq, PartialEq)]
 pub enum ServerType {
     Server,
     Proxy
-}
\ No newline at end of file
+}
@@ -0,0 +1,161 @@
+use std::fmt::{Display, Formatter};
+use crate::backup::create_backup;
+use crate::config::ConfigFile;
+use crate::downloader::Installer;
+use crate::downloaderror::DownloadError;
+use crate::history::{prune_snapshots, save_snapshot};
+use crate::http::{now, Http};
+use crate::install::{execute_plan, plan_install, resolve_version};
+use crate::installplan::{InstallPlan, ServerPlan};
+use crate::instance::Instance;
+use crate::launch::read_ram;
+use crate::os::OS;
+
+/// Which version an upgrade moves to.
+pub enum UpgradeTarget {
+    /// The newest build of the installed Minecraft version.
+    SameVersion,
+    /// The newest Minecraft version.
+    Latest,
+    /// The newest build of this Minecraft version, which may be partial like `1.21`.
+    Version(String),
+}
+
+/// An upgrade from what is installed to a newer build or version, made by [`plan_upgrade`].
+pub struct UpgradePlan {
+    pub from: Instance,
+    pub install: InstallPlan,
+}
+
+impl UpgradePlan {
+    /// Whether the newest build is already installed, so there is nothing to upgrade.
+    pub fn is_current(&self) -> bool {
+        self.from.minecraft_version == self.install.server.minecraft_version
+            && self.from.version == self.install.server.version
+            && self.from.build == self.install.server.build
+    }
+}
+
+impl Display for UpgradePlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}",
+            self.install.distribution,
+            describe(&self.from.minecraft_version, &self.from.version, &self.from.build),
+            describe(&self.install.server.minecraft_version, &self.install.server.version, &self.install.server.build),
+        )?;
+
+        match self.from.java_major {
+            Some(major) if major != self.install.java.major => write!(f, ", Java {} -> {}", major, self.install.java.major),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A newer build or version of an installed server, found by [`check_for_update`].
+pub struct Update {
+    pub installed: Instance,
+    pub available: ServerPlan,
+}
+
+impl Display for Update {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (installed, available) = (&self.installed, &self.available);
+
+        match (&installed.build, &available.build, &installed.version, &available.version) {
+            (Some(from), Some(to), _, _) if installed.minecraft_version == available.minecraft_version && from != to => write!(f, "build {} installed, {} available", from, to),
+            (_, _, Some(from), Some(to)) if installed.minecraft_version == available.minecraft_version => write!(f, "version {} installed, {} available", from, to),
+            _ => write!(
+                f,
+                "{} installed, {} available",
+                describe(&installed.minecraft_version, &installed.version, &installed.build),
+                describe(&available.minecraft_version, &available.version, &available.build),
+            ),
+        }
+    }
+}
+
+/// Asks the upstream of `server_object` for the newest build of the Minecraft version `instance` has installed.
+///
+/// Returns `None` if that is what is installed. Unlike [`plan_upgrade`], this only looks at the server, not Java.
+pub async fn check_for_update(server_object: &dyn Installer, instance: &Instance, http: &Http) -> Result<Option<Update>, DownloadError> {
+    let minecraft_version = instance.minecraft_version.clone().filter(|_| server_object.version_required());
+    let available = server_object.resolve(http.clone(), minecraft_version).await?;
+
+    let current = instance.minecraft_version == available.minecraft_version
+        && instance.version == available.version
+        && instance.build == available.build;
+
+    Ok((!current).then(|| Update { installed: instance.clone(), available }))
+}
+
+/// Works out what upgrading the server described by `instance` to `target` would install.
+///
+/// The RAM setting is kept. The Java version follows the new Minecraft version.
+pub async fn plan_upgrade(server_object: &dyn Installer, instance: &Instance, target: UpgradeTarget, config: &ConfigFile, os: &OS, http: &Http) -> Result<UpgradePlan, DownloadError> {
+    let minecraft_version = match target {
+        UpgradeTarget::SameVersion => instance.minecraft_version.clone().filter(|_| server_object.version_required()),
+        UpgradeTarget::Latest => None,
+        UpgradeTarget::Version(version) => resolve_version(server_object, Some(version), http).await?,
+    };
+
+    let ram = instance.ram.or_else(read_ram).unwrap_or(3);
+    let install = plan_install(server_object, minecraft_version, ram, config, os, http).await?;
+
+    Ok(UpgradePlan { from: instance.clone(), install })
+}
+
+/// Carries out `plan` in the current directory, backing it up first unless `backup` is false.
+///
+/// Worlds, configs, plugins and the port are left in place. Only the server artifact, the launch script and,
+/// for Forge and NeoForge, the files their installer creates are replaced. The replaced install is saved to the
+/// history first, see [`save_snapshot`], and removed from it again if the upgrade fails. Returns where the backup went.
+pub async fn execute_upgrade(server_object: &dyn Installer, plan: &UpgradePlan, backup: bool, config: &ConfigFile, os: &OS, http: &Http) -> Result<Option<String>, DownloadError> {
+    let backup_path = if backup {
+        println!("Backing up the server...");
+
+        let name = format!("{}-{}", now(), server_object.get_id());
+        Some(create_backup(&name, &[&plan.install.java.install_path])?)
+    } else {
+        None
+    };
+
+    let keep = config.get_history_settings()?.keep;
+    let snapshot = save_snapshot(&plan.from, backup_path.clone(), keep)?;
+
+    if let Err(error) = execute_plan(server_object, &plan.install, os, http).await {
+        // Rolling back to the install that is still there would change nothing, so the snapshot is dropped again.
+        if let Some(Err(delete_error)) = snapshot.map(|snapshot| snapshot.delete(false)) {
+            eprintln!("Warning: the history entry for this upgrade could not be removed: {}", delete_error);
+        }
+
+        return Err(error);
+    }
+
+    prune_snapshots(keep)?;
+
+    Ok(backup_path)
+}
+
+pub(crate) fn describe(minecraft_version: &Option<String>, version: &Option<String>, build: &Option<String>) -> String {
+    let mut parts = vec![];
+
+    if let Some(minecraft_version) = minecraft_version {
+        parts.push(minecraft_version.clone());
+    }
+
+    if let Some(version) = version {
+        parts.push(version.clone());
+    }
+
+    if let Some(build) = build {
+        parts.push(format!("build {}", build));
+    }
+
+    if parts.is_empty() {
+        "unknown version".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter};
+use crate::config::default_java_version;
+use crate::minecraftversion::MinecraftVersion;
+
+/// What kind of build a version is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionKind {
+    Release,
+    Snapshot,
+    PreRelease,
+    ReleaseCandidate,
+    /// Experimental snapshots, April Fools versions, combat tests and anything else outside the usual cycle.
+    Experimental,
+}
+
+/// A version an [`Installer`](crate::downloader::Installer) can install.
+#[derive(Clone, Debug)]
+pub struct VersionInfo {
+    pub id: String,
+    pub kind: VersionKind,
+    /// When the version was released, as an RFC 3339 timestamp, if the upstream publishes it.
+    pub release_time: Option<String>,
+    /// Whether the upstream recommends this version for general use.
+    pub stable: bool,
+    /// The Java major version needed to run this version. `None` for proxies and Geyser, whose versions are not
+    /// Minecraft versions and which run on the Java version of the latest release.
+    pub java_major: Option<i32>,
+}
+
+impl VersionInfo {
+    /// Describes a Minecraft version, guessing its kind from the ID. Only releases are considered stable.
+    ///
+    /// The Java version follows the default thresholds, see [`ConfigFile::set_java_versions`](crate::config::ConfigFile::set_java_versions)
+    /// to apply the configured ones.
+    pub fn from_id(id: &str) -> VersionInfo {
+        let minecraft_version = MinecraftVersion::parse(id);
+        let kind = minecraft_version.kind();
+
+        VersionInfo {
+            id: id.to_string(),
+            kind,
+            release_time: None,
+            stable: kind == VersionKind::Release,
+            java_major: Some(default_java_version(&minecraft_version)),
+        }
+    }
+}
+
+impl VersionKind {
+    /// Guesses the kind of a Minecraft version from its ID, e.g. `24w14a` is a snapshot and `1.20.5-rc1` a release candidate.
+    pub fn from_id(id: &str) -> VersionKind {
+        MinecraftVersion::parse(id).kind()
+    }
+}
+
+impl Display for VersionKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            VersionKind::Release => "release",
+            VersionKind::Snapshot => "snapshot",
+            VersionKind::PreRelease => "pre-release",
+            VersionKind::ReleaseCandidate => "release candidate",
+            VersionKind::Experimental => "experimental",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl Display for VersionInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
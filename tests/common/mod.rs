@@ -0,0 +1,140 @@
+//! A local stand-in for every upstream the installers use, serving the recorded responses in `tests/fixtures`.
+
+// Each test binary uses a different part of this module.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+use minecraft_server_installer::http::Http;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+/// The upstream hosts, each served by the mock under `/{host}/`.
+const HOSTS: &[&str] = &[
+    "launchermeta.mojang.com",
+    "piston-meta.mojang.com",
+    "piston-data.mojang.com",
+    "api.papermc.io",
+    "api.purpurmc.org",
+    "meta.fabricmc.net",
+    "files.minecraftforge.net",
+    "maven.neoforged.net",
+    "download.geysermc.org",
+    "ci.md-5.net",
+    "api.github.com",
+    "github.com",
+];
+
+/// Request paths and the fixtures served for them.
+const ROUTES: &[(&str, &str)] = &[
+    ("/launchermeta.mojang.com/mc/game/version_manifest.json", "mojang/version_manifest.json"),
+    ("/piston-meta.mojang.com/v1/packages/c98adde5094a3041f486b4d42d0386cf87310559/1.20.4.json", "mojang/1.20.4.json"),
+    ("/piston-data.mojang.com/v1/objects/8a89cb914a61bc9216f5713025ae542872ae62a1/server.jar", "artifacts/vanilla-1.20.4.jar"),
+    ("/api.papermc.io/v2/projects/paper", "papermc/paper.json"),
+    ("/api.papermc.io/v2/projects/paper/versions/1.20.4/builds", "papermc/paper-1.20.4-builds.json"),
+    ("/api.papermc.io/v2/projects/paper/versions/1.20.4/builds/499/downloads/paper-1.20.4-499.jar", "artifacts/paper-1.20.4-499.jar"),
+    ("/api.papermc.io/v2/projects/velocity", "papermc/velocity.json"),
+    ("/api.papermc.io/v2/projects/velocity/versions/3.3.0-SNAPSHOT/builds", "papermc/velocity-3.3.0-SNAPSHOT-builds.json"),
+    ("/api.papermc.io/v2/projects/velocity/versions/3.3.0-SNAPSHOT/builds/436/downloads/velocity-3.3.0-SNAPSHOT-436.jar", "artifacts/velocity-3.3.0-SNAPSHOT-436.jar"),
+    ("/api.papermc.io/v2/projects/waterfall", "papermc/waterfall.json"),
+    ("/api.papermc.io/v2/projects/waterfall/versions/1.20/builds", "papermc/waterfall-1.20-builds.json"),
+    ("/api.papermc.io/v2/projects/waterfall/versions/1.20/builds/569/downloads/waterfall-1.20-569.jar", "artifacts/waterfall-1.20-569.jar"),
+    ("/api.purpurmc.org/v2/purpur/", "purpur/purpur.json"),
+    ("/api.purpurmc.org/v2/purpur/1.20.4", "purpur/purpur-1.20.4.json"),
+    ("/api.purpurmc.org/v2/purpur/1.20.4/2175", "purpur/purpur-1.20.4-2175.json"),
+    ("/api.purpurmc.org/v2/purpur/1.20.4/2176", "purpur/purpur-1.20.4-2176.json"),
+    ("/api.purpurmc.org/v2/purpur/1.20.4/2176/download", "artifacts/purpur-1.20.4-2176.jar"),
+    ("/meta.fabricmc.net/v2/versions", "fabric/versions.json"),
+    ("/meta.fabricmc.net/v2/versions/game", "fabric/game.json"),
+    ("/meta.fabricmc.net/v2/versions/loader", "fabric/loader.json"),
+    ("/meta.fabricmc.net/v2/versions/loader/1.20.4/0.15.11/1.0.0/server/jar", "artifacts/fabric-1.20.4-0.15.11.jar"),
+    ("/api.github.com/repos/FabricMC/fabric-loader/releases", "github/fabric-loader-releases.json"),
+    ("/api.github.com/repositories/120717019/releases", "github/fabric-loader-releases-2.json"),
+    ("/files.minecraftforge.net/maven/net/minecraftforge/forge/promotions_slim.json", "forge/promotions_slim.json"),
+    ("/files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json", "forge/promotions_slim.json"),
+    ("/files.minecraftforge.net/maven/net/minecraftforge/forge/1.20.4-49.1.0/forge-1.20.4-49.1.0-installer.jar", "artifacts/forge-1.20.4-49.1.0.jar"),
+    ("/files.minecraftforge.net/maven/net/minecraftforge/forge/1.20.4-49.1.0/forge-1.20.4-49.1.0-installer.jar.sha1", "forge/forge-1.20.4-49.1.0-installer.jar.sha1"),
+    ("/maven.neoforged.net/releases/net/neoforged/neoforge/maven-metadata.xml", "neoforge/maven-metadata.xml"),
+    ("/maven.neoforged.net/releases/net/neoforged/neoforge/20.4.237/neoforge-20.4.237-installer.jar", "artifacts/neoforge-20.4.237.jar"),
+    ("/maven.neoforged.net/releases/net/neoforged/neoforge/20.4.237/neoforge-20.4.237-installer.jar.sha1", "neoforge/neoforge-20.4.237-installer.jar.sha1"),
+    ("/maven.neoforged.net/releases/net/neoforged/neoforge/20.4.237/neoforge-20.4.237-changelog.txt", "neoforge/neoforge-20.4.237-changelog.txt"),
+    ("/download.geysermc.org/v2/projects/geyser/versions/latest", "geyser/latest.json"),
+    ("/download.geysermc.org/v2/projects/geyser/versions/latest/builds/latest", "geyser/latest-build.json"),
+    ("/download.geysermc.org/v2/projects/geyser/versions/2.4.2/builds/650/downloads/standalone", "artifacts/geyser-2.4.2-650.jar"),
+    ("/ci.md-5.net/job/BungeeCord/lastSuccessfulBuild/buildNumber", "jenkins/buildNumber"),
+    ("/ci.md-5.net/job/BungeeCord/1870/artifact/bootstrap/target/BungeeCord.jar", "artifacts/bungeecord-1870.jar"),
+];
+
+/// A running mock of all upstreams. Anything not in [`ROUTES`] answers with 404.
+pub struct Upstreams {
+    server: MockServer,
+}
+
+impl Upstreams {
+    pub async fn start() -> Upstreams {
+        let server = MockServer::start().await;
+
+        for (route, name) in ROUTES {
+            Mock::given(method("GET"))
+                .and(path(*route))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(fixture(name)))
+                .mount(&server)
+                .await;
+        }
+
+        Upstreams { server }
+    }
+
+    /// Answers requests for `route` with `response` instead of its fixture.
+    pub async fn respond(&self, route: &str, response: ResponseTemplate) {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(response)
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answers only the next request for `route` with `response`, then serves its fixture again.
+    pub async fn respond_once(&self, route: &str, response: ResponseTemplate) {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(response)
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// The requests received for `route` so far, oldest first.
+    pub async fn requests(&self, route: &str) -> Vec<Request> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|request| request.url.path() == route)
+            .collect()
+    }
+
+    /// Maps every upstream to the mock, as the `[upstreams]` config section would.
+    pub fn mirrors(&self) -> Vec<(String, Vec<String>)> {
+        HOSTS
+            .iter()
+            .map(|host| (format!("https://{}/", host), vec![format!("{}/{}/", self.server.uri(), host)]))
+            .collect()
+    }
+
+    /// A client that sends every upstream request to the mock, without a cache.
+    pub fn http(&self) -> Http {
+        Http::new(Client::new(), None, false).with_upstreams(self.mirrors())
+    }
+}
+
+/// Reads a file from `tests/fixtures`.
+pub fn fixture(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+
+    fs::read(&path).unwrap_or_else(|error| panic!("Failed to read fixture {}: {}", path.display(), error))
+}
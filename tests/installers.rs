@@ -0,0 +1,495 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use common::{fixture, Upstreams};
+use minecraft_server_installer::checksum::ChecksumAlgorithm;
+use minecraft_server_installer::downloader::Installer;
+use minecraft_server_installer::downloaderror::DownloadError;
+use minecraft_server_installer::downloaders::bungeecord::BungeeCord;
+use minecraft_server_installer::downloaders::fabric::Fabric;
+use minecraft_server_installer::downloaders::forge::Forge;
+use minecraft_server_installer::downloaders::geyser::Geyser;
+use minecraft_server_installer::downloaders::neoforge::NeoForge;
+use minecraft_server_installer::downloaders::paper::Paper;
+use minecraft_server_installer::downloaders::purpur::Purpur;
+use minecraft_server_installer::downloaders::vanilla::Vanilla;
+use minecraft_server_installer::downloaders::velocity::Velocity;
+use minecraft_server_installer::downloaders::waterfall::Waterfall;
+use minecraft_server_installer::http::Http;
+use minecraft_server_installer::install::resolve_version;
+use minecraft_server_installer::installplan::{InstallPlan, JavaPlan, ServerPlan};
+use minecraft_server_installer::instance::Instance;
+use minecraft_server_installer::lockfile::Lockfile;
+use minecraft_server_installer::os::OS;
+use minecraft_server_installer::upgrade::check_for_update;
+use minecraft_server_installer::versioninfo::VersionKind;
+use reqwest::Client;
+use tempfile::TempDir;
+use wiremock::ResponseTemplate;
+
+/// Resolves `minecraft_version` like the CLI does and downloads the result into a temporary directory.
+async fn install(installer: &dyn Installer, http: &Http, minecraft_version: Option<&str>) -> Result<(ServerPlan, Vec<u8>), DownloadError> {
+    let directory = TempDir::new().expect("Failed to create temporary directory");
+    let minecraft_version = resolve_version(installer, minecraft_version.map(|version| version.to_string()), http).await?;
+
+    let mut plan = installer.resolve(http.clone(), minecraft_version).await?;
+    plan.artifact.path = directory.path().join("server.jar").to_string_lossy().to_string();
+
+    installer.download(http.clone(), &plan).await?;
+
+    let content = fs::read(&plan.artifact.path).expect("Failed to read downloaded file");
+
+    Ok((plan, content))
+}
+
+async fn version_ids(installer: &dyn Installer, http: &Http) -> Vec<String> {
+    installer.get_versions(http.clone()).await.expect("Failed to get versions").into_iter().map(|version| version.id).collect()
+}
+
+#[tokio::test]
+async fn vanilla_lists_versions_oldest_first() {
+    let upstreams = Upstreams::start().await;
+    let versions = Vanilla {}.get_versions(upstreams.http()).await.unwrap();
+
+    assert_eq!(versions.first().unwrap().id, "b1.7.3");
+    assert_eq!(versions.last().unwrap().id, "1.20.5-rc1");
+
+    let snapshot = versions.iter().find(|version| version.id == "24w14a").unwrap();
+    assert_eq!(snapshot.kind, VersionKind::Snapshot);
+    assert!(!snapshot.stable);
+    assert_eq!(snapshot.java_major, Some(21));
+
+    let release = versions.iter().find(|version| version.id == "1.20.4").unwrap();
+    assert_eq!(release.kind, VersionKind::Release);
+    assert_eq!(release.release_time.as_deref(), Some("2023-12-07T12:56:20+00:00"));
+    assert!(release.stable);
+    assert_eq!(release.java_major, Some(17));
+
+    let beta = versions.first().unwrap();
+    assert_eq!(beta.kind, VersionKind::Experimental);
+    assert_eq!(beta.java_major, Some(8));
+}
+
+#[tokio::test]
+async fn vanilla_downloads_server() {
+    let upstreams = Upstreams::start().await;
+    let (plan, content) = install(&Vanilla {}, &upstreams.http(), Some("1.20.4")).await.unwrap();
+
+    assert_eq!(plan.minecraft_version.as_deref(), Some("1.20.4"));
+    assert_eq!(plan.artifact.checksum.unwrap().algorithm, ChecksumAlgorithm::Sha1);
+    assert_eq!(content, fixture("artifacts/vanilla-1.20.4.jar"));
+}
+
+#[tokio::test]
+async fn vanilla_defaults_to_latest_release() {
+    let upstreams = Upstreams::start().await;
+    let (plan, _) = install(&Vanilla {}, &upstreams.http(), None).await.unwrap();
+
+    assert_eq!(plan.minecraft_version.as_deref(), Some("1.20.4"));
+}
+
+#[tokio::test]
+async fn unknown_versions_suggest_similar_ones() {
+    let upstreams = Upstreams::start().await;
+    let error = install(&Vanilla {}, &upstreams.http(), Some("1.20.9")).await.unwrap_err();
+
+    match error {
+        DownloadError::VersionNotFound { version, suggestions } => {
+            assert_eq!(version, "1.20.9");
+            assert!(suggestions.contains(&"1.20.4".to_string()), "{:?}", suggestions);
+        }
+        error => panic!("Expected VersionNotFound, got {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn paper_uses_newest_default_channel_build() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    assert_eq!(version_ids(&Paper {}, &http).await.last().unwrap(), "1.20.4");
+
+    let (plan, content) = install(&Paper {}, &http, Some("1.20.4")).await.unwrap();
+
+    assert_eq!(plan.build.as_deref(), Some("499"));
+    assert_eq!(plan.artifact.checksum.unwrap().algorithm, ChecksumAlgorithm::Sha256);
+    assert_eq!(content, fixture("artifacts/paper-1.20.4-499.jar"));
+}
+
+#[tokio::test]
+async fn purpur_verifies_md5() {
+    let upstreams = Upstreams::start().await;
+    let (plan, content) = install(&Purpur {}, &upstreams.http(), Some("1.20.4")).await.unwrap();
+
+    assert_eq!(plan.build.as_deref(), Some("2176"));
+    assert_eq!(plan.artifact.checksum.unwrap().algorithm, ChecksumAlgorithm::Md5);
+    assert_eq!(content, fixture("artifacts/purpur-1.20.4-2176.jar"));
+}
+
+#[tokio::test]
+async fn fabric_uses_latest_stable_game_and_loader() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    let versions = Fabric {}.get_versions(http.clone()).await.unwrap();
+    assert_eq!(versions.last().unwrap().id, "1.20.5-rc1");
+    assert!(versions.iter().find(|version| version.id == "1.20.3").unwrap().stable);
+    assert!(!versions.iter().find(|version| version.id == "24w14a").unwrap().stable);
+
+    let (plan, content) = install(&Fabric {}, &http, None).await.unwrap();
+
+    assert_eq!(plan.minecraft_version.as_deref(), Some("1.20.4"));
+    assert_eq!(plan.version.as_deref(), Some("0.15.11"));
+    assert_eq!(content, fixture("artifacts/fabric-1.20.4-0.15.11.jar"));
+}
+
+#[tokio::test]
+async fn forge_uses_newest_build_and_published_sha1() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    let versions = Forge {}.get_versions(http.clone()).await.unwrap();
+    assert!(versions.iter().find(|version| version.id == "1.20.4").unwrap().stable);
+    assert!(!versions.iter().find(|version| version.id == "1.20.3").unwrap().stable);
+
+    let (plan, content) = install(&Forge {}, &http, Some("1.20.4")).await.unwrap();
+
+    assert_eq!(plan.version.as_deref(), Some("49.1.0"));
+    assert_eq!(plan.artifact.checksum.unwrap().value, "075fd9df6f2f7c3ec0412bec658bf03abed01ce4");
+    assert_eq!(content, fixture("artifacts/forge-1.20.4-49.1.0.jar"));
+}
+
+#[tokio::test]
+async fn neoforge_resolves_partial_versions() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    assert_eq!(version_ids(&NeoForge {}, &http).await, vec!["1.20.2", "1.20.4"]);
+
+    let (plan, content) = install(&NeoForge {}, &http, Some("1.20")).await.unwrap();
+
+    assert_eq!(plan.minecraft_version.as_deref(), Some("1.20.4"));
+    assert_eq!(plan.version.as_deref(), Some("20.4.237"));
+    assert_eq!(plan.artifact.checksum.unwrap().algorithm, ChecksumAlgorithm::Sha1);
+    assert_eq!(content, fixture("artifacts/neoforge-20.4.237.jar"));
+
+    let error = NeoForge {}.resolve(http, Some("1.20.3".to_string())).await.unwrap_err();
+
+    match error {
+        DownloadError::VersionNotFound { suggestions, .. } => assert_eq!(suggestions, vec!["1.20.4", "1.20.2"]),
+        error => panic!("Unexpected error: {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn geyser_downloads_latest_build() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    assert_eq!(version_ids(&Geyser {}, &http).await, vec!["2.4.2"]);
+
+    let (plan, content) = install(&Geyser {}, &http, None).await.unwrap();
+
+    assert_eq!(plan.version.as_deref(), Some("2.4.2"));
+    assert_eq!(plan.build.as_deref(), Some("650"));
+    assert_eq!(content, fixture("artifacts/geyser-2.4.2-650.jar"));
+}
+
+#[tokio::test]
+async fn bungeecord_downloads_last_successful_build() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    assert_eq!(version_ids(&BungeeCord {}, &http).await, vec!["1870"]);
+
+    let (plan, content) = install(&BungeeCord {}, &http, None).await.unwrap();
+
+    assert_eq!(plan.build.as_deref(), Some("1870"));
+    assert!(plan.artifact.checksum.is_none());
+    assert_eq!(content, fixture("artifacts/bungeecord-1870.jar"));
+}
+
+#[tokio::test]
+async fn velocity_marks_snapshots_unstable() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    let versions = Velocity {}.get_versions(http.clone()).await.unwrap();
+    assert_eq!(versions[0].id, "3.3.0-SNAPSHOT");
+    assert_eq!(versions[0].kind, VersionKind::Snapshot);
+    assert!(!versions[0].stable);
+    assert_eq!(versions[0].java_major, None);
+
+    let (plan, content) = install(&Velocity {}, &http, None).await.unwrap();
+
+    assert_eq!(plan.build.as_deref(), Some("436"));
+    assert_eq!(content, fixture("artifacts/velocity-3.3.0-SNAPSHOT-436.jar"));
+}
+
+#[tokio::test]
+async fn waterfall_downloads_latest_version() {
+    let upstreams = Upstreams::start().await;
+    let (plan, content) = install(&Waterfall {}, &upstreams.http(), None).await.unwrap();
+
+    assert_eq!(plan.minecraft_version.as_deref(), Some("1.20"));
+    assert_eq!(plan.build.as_deref(), Some("569"));
+    assert_eq!(content, fixture("artifacts/waterfall-1.20-569.jar"));
+}
+
+#[tokio::test]
+async fn checksum_mismatch_removes_download() {
+    let upstreams = Upstreams::start().await;
+    upstreams.respond(
+        "/api.papermc.io/v2/projects/paper/versions/1.20.4/builds/499/downloads/paper-1.20.4-499.jar",
+        ResponseTemplate::new(200).set_body_string("tampered"),
+    ).await;
+
+    let http = upstreams.http();
+    let directory = TempDir::new().unwrap();
+    let mut plan = Paper {}.resolve(http.clone(), Some("1.20.4".to_string())).await.unwrap();
+    plan.artifact.path = directory.path().join("server.jar").to_string_lossy().to_string();
+
+    let error = Paper {}.download(http, &plan).await.unwrap_err();
+
+    assert!(matches!(error, DownloadError::ChecksumMismatch { .. }), "{:?}", error);
+    assert!(!Path::new(&plan.artifact.path).exists());
+    assert!(!Path::new(&format!("{}.part", plan.artifact.path)).exists());
+}
+
+#[tokio::test]
+async fn interrupted_download_is_resumed_with_a_range_request() {
+    let route = "/api.papermc.io/v2/projects/paper/versions/1.20.4/builds/499/downloads/paper-1.20.4-499.jar";
+    let jar = fixture("artifacts/paper-1.20.4-499.jar");
+    let split = jar.len() / 2;
+
+    let upstreams = Upstreams::start().await;
+    upstreams.respond(
+        route,
+        ResponseTemplate::new(206)
+            .insert_header("Content-Range", format!("bytes {}-{}/{}", split, jar.len() - 1, jar.len()).as_str())
+            .set_body_bytes(jar[split..].to_vec()),
+    ).await;
+
+    let http = upstreams.http();
+    let directory = TempDir::new().unwrap();
+    let mut plan = Paper {}.resolve(http.clone(), Some("1.20.4".to_string())).await.unwrap();
+    plan.artifact.path = directory.path().join("server.jar").to_string_lossy().to_string();
+    fs::write(format!("{}.part", plan.artifact.path), &jar[..split]).unwrap();
+
+    Paper {}.download(http, &plan).await.unwrap();
+
+    let requests = upstreams.requests(route).await;
+    let range = requests[0].headers.get("Range").and_then(|range| range.to_str().ok());
+
+    assert_eq!(requests.len(), 1);
+    assert_eq!(range, Some(format!("bytes={}-", split).as_str()));
+    assert_eq!(fs::read(&plan.artifact.path).unwrap(), jar);
+}
+
+#[tokio::test]
+async fn rate_limited_download_waits_for_retry_after() {
+    let route = "/api.papermc.io/v2/projects/paper/versions/1.20.4/builds/499/downloads/paper-1.20.4-499.jar";
+
+    let upstreams = Upstreams::start().await;
+    upstreams.respond_once(route, ResponseTemplate::new(429).insert_header("Retry-After", "0")).await;
+
+    let http = upstreams.http();
+    let directory = TempDir::new().unwrap();
+    let mut plan = Paper {}.resolve(http.clone(), Some("1.20.4".to_string())).await.unwrap();
+    plan.artifact.path = directory.path().join("server.jar").to_string_lossy().to_string();
+
+    let started = Instant::now();
+    Paper {}.download(http, &plan).await.unwrap();
+
+    // Without Retry-After, the first retry would wait a second.
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(upstreams.requests(route).await.len(), 2);
+    assert_eq!(fs::read(&plan.artifact.path).unwrap(), fixture("artifacts/paper-1.20.4-499.jar"));
+}
+
+#[tokio::test]
+async fn changed_upstream_schema_is_reported() {
+    let upstreams = Upstreams::start().await;
+    upstreams.respond("/api.purpurmc.org/v2/purpur/", ResponseTemplate::new(200).set_body_string("{\"project\": \"purpur\"}")).await;
+
+    let error = Purpur {}.get_versions(upstreams.http()).await.unwrap_err();
+
+    assert!(matches!(error, DownloadError::Schema { .. }), "{:?}", error);
+}
+
+#[tokio::test]
+async fn unreachable_mirror_falls_back_to_next() {
+    let upstreams = Upstreams::start().await;
+    let mirrors = upstreams
+        .mirrors()
+        .into_iter()
+        .map(|(prefix, mirrors)| (prefix, [vec!["http://127.0.0.1:9/".to_string()], mirrors].concat()))
+        .collect();
+
+    let http = Http::new(Client::new(), None, false).with_upstreams(mirrors);
+    let plan = Geyser {}.resolve(http, None).await.unwrap();
+
+    assert_eq!(plan.build.as_deref(), Some("650"));
+}
+
+#[tokio::test]
+async fn lockfile_pins_downloaded_artifact() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+    let directory = TempDir::new().unwrap();
+    let mut server = Paper {}.resolve(http.clone(), Some("1.20.4".to_string())).await.unwrap();
+    server.artifact.path = directory.path().join("server.jar").to_string_lossy().to_string();
+
+    let mut hasher = ChecksumAlgorithm::Sha256.hasher();
+    hasher.update(&fixture("artifacts/paper-1.20.4-499.jar"));
+
+    let plan = InstallPlan {
+        distribution: Paper {}.get_name(),
+        server,
+        java: JavaPlan {
+            major: 17,
+            url: "https://api.adoptium.net/v3/binary/latest/17/ga/linux/x64/jdk/hotspot/normal/eclipse".to_string(),
+            install_path: "java/".to_string(),
+            executable: "java/jdk-17/bin/java".to_string(),
+            checksum: None,
+            installed: true,
+        },
+        ram: 3,
+        files: vec![],
+    };
+
+    let lockfile = Lockfile::new(&Paper {}, &plan, hasher.finish(), &OS::Linux);
+    let lockfile: Lockfile = toml::from_str(&toml::to_string(&lockfile).unwrap()).unwrap();
+
+    assert_eq!(lockfile.distribution, "paper");
+    assert_eq!(lockfile.server.build.as_deref(), Some("499"));
+    assert_eq!(lockfile.server.artifact.checksum.as_ref().unwrap().algorithm, ChecksumAlgorithm::Sha256);
+
+    Paper {}.download(http.clone(), &lockfile.server).await.unwrap();
+    assert_eq!(fs::read(&lockfile.server.artifact.path).unwrap(), fixture("artifacts/paper-1.20.4-499.jar"));
+
+    fs::remove_file(&lockfile.server.artifact.path).unwrap();
+    upstreams.respond(
+        "/api.papermc.io/v2/projects/paper/versions/1.20.4/builds/499/downloads/paper-1.20.4-499.jar",
+        ResponseTemplate::new(200).set_body_string("rebuilt"),
+    ).await;
+
+    let error = Paper {}.download(http, &lockfile.server).await.unwrap_err();
+
+    assert!(matches!(error, DownloadError::ChecksumMismatch { .. }), "{:?}", error);
+}
+
+#[tokio::test]
+async fn newer_builds_are_reported_as_updates() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    let mut paper = Instance {
+        distribution: "paper".to_string(),
+        minecraft_version: Some("1.20.4".to_string()),
+        build: Some("400".to_string()),
+        ..Instance::default()
+    };
+
+    let update = check_for_update(&Paper {}, &paper, &http).await.unwrap().unwrap();
+    assert_eq!(update.to_string(), "build 400 installed, 499 available");
+
+    paper.build = Some("499".to_string());
+    assert!(check_for_update(&Paper {}, &paper, &http).await.unwrap().is_none());
+
+    let fabric = Instance {
+        distribution: "fabric".to_string(),
+        minecraft_version: Some("1.20.4".to_string()),
+        version: Some("0.15.0".to_string()),
+        ..Instance::default()
+    };
+
+    let update = check_for_update(&Fabric {}, &fabric, &http).await.unwrap().unwrap();
+    assert_eq!(update.to_string(), "version 0.15.0 installed, 0.15.11 available");
+}
+
+#[tokio::test]
+async fn changelog_lists_builds_after_the_installed_one() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+
+    let paper = Instance {
+        distribution: "paper".to_string(),
+        minecraft_version: Some("1.20.4".to_string()),
+        build: Some("497".to_string()),
+        ..Instance::default()
+    };
+
+    let builds = Paper {}.changelog(http.clone(), &paper, None).await.unwrap().unwrap();
+    let listed = builds.iter().map(|build| build.to_string()).collect::<Vec<String>>();
+
+    assert_eq!(listed, vec![
+        "499 (2024-04-25)\n  - Fix chunk loading deadlock on shutdown\n  - Backport entity tracking fixes",
+        "500 (2024-04-26) [experimental]\n  - Rewrite the light engine",
+    ]);
+
+    let builds = Paper {}.changelog(http.clone(), &paper, Some("499".to_string())).await.unwrap().unwrap();
+    assert_eq!(builds.iter().map(|build| build.build.as_str()).collect::<Vec<&str>>(), vec!["499"]);
+
+    let purpur = Instance {
+        distribution: "purpur".to_string(),
+        minecraft_version: Some("1.20.4".to_string()),
+        build: Some("2174".to_string()),
+        ..Instance::default()
+    };
+
+    let builds = Purpur {}.changelog(http.clone(), &purpur, None).await.unwrap().unwrap();
+
+    assert_eq!(builds.iter().map(|build| build.build.as_str()).collect::<Vec<&str>>(), vec!["2175", "2176"]);
+    assert_eq!(builds[0].changes, vec!["Updated Upstream (Paper)"]);
+    assert_eq!(builds[1].date.as_deref(), Some("2024-04-25"));
+
+    let fabric = Instance {
+        distribution: "fabric".to_string(),
+        minecraft_version: Some("1.20.4".to_string()),
+        version: Some("0.15.9".to_string()),
+        ..Instance::default()
+    };
+
+    let releases_route = "/api.github.com/repos/FabricMC/fabric-loader/releases";
+    let first_page = ResponseTemplate::new(200)
+        .set_body_bytes(fixture("github/fabric-loader-releases.json"))
+        .insert_header("Link", "<https://api.github.com/repositories/120717019/releases?per_page=100&page=2>; rel=\"next\", <https://api.github.com/repositories/120717019/releases?per_page=100&page=2>; rel=\"last\"");
+    upstreams.respond(releases_route, first_page).await;
+
+    let builds = Fabric {}.changelog(http.clone(), &fabric, None).await.unwrap().unwrap();
+
+    assert_eq!(builds.iter().map(|build| build.build.as_str()).collect::<Vec<&str>>(), vec!["0.15.10", "0.15.11", "0.16.0-beta.1"]);
+    assert_eq!(builds[0].changes, vec!["Update Mixin to 0.13.4"]);
+    assert_eq!(builds[1].date.as_deref(), Some("2024-05-01"));
+    assert!(builds[2].experimental);
+    assert_eq!(upstreams.requests(releases_route).await[0].headers["accept"], "application/vnd.github+json");
+    assert_eq!(upstreams.requests("/api.github.com/repositories/120717019/releases").await.len(), 1);
+
+    let neoforge = Instance {
+        distribution: "neoforge".to_string(),
+        minecraft_version: Some("1.20.4".to_string()),
+        version: Some("20.4.235".to_string()),
+        ..Instance::default()
+    };
+
+    let builds = NeoForge {}.changelog(http.clone(), &neoforge, None).await.unwrap().unwrap();
+
+    assert_eq!(builds.iter().map(|build| build.build.as_str()).collect::<Vec<&str>>(), vec!["20.4.236", "20.4.237"]);
+    assert_eq!(builds[1].changes, vec!["Fix fluid rendering in item frames (#812)", "Update to the latest FML (#815)"]);
+
+    let vanilla = Instance { distribution: "vanilla".to_string(), ..Instance::default() };
+    assert!(Vanilla {}.changelog(http, &vanilla, None).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn rate_limited_changelog_is_skipped() {
+    let upstreams = Upstreams::start().await;
+    upstreams.respond("/api.github.com/repos/FabricMC/fabric-loader/releases", ResponseTemplate::new(403)).await;
+
+    let fabric = Instance { distribution: "fabric".to_string(), version: Some("0.15.9".to_string()), ..Instance::default() };
+
+    assert!(Fabric {}.changelog(upstreams.http(), &fabric, None).await.unwrap().is_none());
+}
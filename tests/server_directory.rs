@@ -0,0 +1,200 @@
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use common::{fixture, Upstreams};
+use minecraft_server_installer::backup::create_backup;
+use minecraft_server_installer::config::ConfigFile;
+use minecraft_server_installer::downloaders::paper::Paper;
+use minecraft_server_installer::history::{prune_snapshots, restore_snapshot, save_snapshot, snapshots};
+use minecraft_server_installer::instance::Instance;
+use minecraft_server_installer::manifest::{Manifest, MANIFEST_FILE};
+use minecraft_server_installer::os::OS;
+use minecraft_server_installer::upgrade::{execute_upgrade, plan_upgrade, UpgradeTarget};
+use tempfile::TempDir;
+use wiremock::ResponseTemplate;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Tests here work in the current directory, which the whole process shares, so they take turns.
+static CURRENT_DIRECTORY: Mutex<()> = Mutex::const_new(());
+
+/// Makes `directory` the current directory until the returned guard is dropped.
+async fn enter(directory: &TempDir) -> MutexGuard<'static, ()> {
+    let guard = CURRENT_DIRECTORY.lock().await;
+    env::set_current_dir(directory.path()).expect("Failed to enter temporary directory");
+
+    guard
+}
+
+/// Writes the default config into `directory` with Java 17 already installed, so nothing is downloaded for Java.
+fn config(directory: &TempDir) -> ConfigFile {
+    let config = ConfigFile::new(directory.path().to_string_lossy().to_string());
+    config.create();
+
+    fs::create_dir_all("java/jdk-17.0.10+7-jre/bin").unwrap();
+    fs::write("java/jdk-17.0.10+7-jre/bin/java", "").unwrap();
+
+    config
+}
+
+/// Writes a Paper install whose lockfile lists the server jar as `./server.jar`, like the installers record it.
+fn install_paper(build: &str) -> Instance {
+    let instance = Instance {
+        distribution: "paper".to_string(),
+        minecraft_version: Some("1.20.4".to_string()),
+        build: Some(build.to_string()),
+        ..Instance::default()
+    };
+
+    fs::write("server.jar", format!("paper {}", build)).unwrap();
+    fs::write("launch.sh", "java -jar server.jar").unwrap();
+    fs::write("msi-lock.toml", format!(
+        "distribution = \"paper\"\n\n[server]\nminecraft_version = \"1.20.4\"\nbuild = \"{}\"\nfiles = [\"server.jar\"]\n\n\
+         [server.artifact]\nurl = \"https://api.papermc.io/server.jar\"\npath = \"./server.jar\"\n\n\
+         [java]\nmajor = 17\nplatform = \"linux\"\nurl = \"https://github.com/java.tar.gz\"\n",
+        build,
+    )).unwrap();
+    instance.save().unwrap();
+
+    instance
+}
+
+#[tokio::test]
+async fn restoring_a_snapshot_brings_back_the_replaced_install() {
+    let directory = TempDir::new().unwrap();
+    let _guard = enter(&directory).await;
+
+    let instance = install_paper("498");
+    let snapshot = save_snapshot(&instance, None, 5).unwrap().unwrap();
+
+    assert_eq!(snapshot.files, vec!["server.jar", "launch.sh", "msi-instance.toml", "msi-lock.toml"]);
+
+    install_paper("499");
+    fs::create_dir("world").unwrap();
+    restore_snapshot(&snapshot, false).unwrap();
+
+    assert_eq!(fs::read_to_string("server.jar").unwrap(), "paper 498");
+    assert_eq!(Instance::load().unwrap().build.as_deref(), Some("498"));
+    assert!(Path::new("world").is_dir());
+    assert!(snapshots().is_empty());
+}
+
+#[tokio::test]
+async fn restoring_with_the_backup_brings_back_worlds() {
+    let directory = TempDir::new().unwrap();
+    let _guard = enter(&directory).await;
+
+    let instance = install_paper("498");
+    fs::create_dir("world").unwrap();
+    fs::write("world/level.dat", "before").unwrap();
+
+    let backup = create_backup("before-upgrade", &[]).unwrap();
+    save_snapshot(&instance, Some(backup.clone()), 5).unwrap();
+
+    install_paper("499");
+    fs::write("world/level.dat", "after").unwrap();
+
+    let snapshot = snapshots().remove(0);
+    assert_eq!(snapshot.backup, Some(backup.clone()));
+
+    restore_snapshot(&snapshot, true).unwrap();
+
+    assert_eq!(fs::read_to_string("server.jar").unwrap(), "paper 498");
+    assert_eq!(fs::read_to_string("world/level.dat").unwrap(), "before");
+    assert!(Path::new(&backup).is_dir());
+}
+
+#[tokio::test]
+async fn history_is_pruned_to_the_configured_size() {
+    let directory = TempDir::new().unwrap();
+    let _guard = enter(&directory).await;
+
+    for build in ["497", "498", "499"] {
+        let instance = install_paper(build);
+        let backup = create_backup(&format!("before-{}", build), &[]).unwrap();
+
+        save_snapshot(&instance, Some(backup), 2).unwrap();
+    }
+
+    // All three are usually taken within the same second, so none may replace another.
+    assert_eq!(snapshots().len(), 3);
+
+    prune_snapshots(2).unwrap();
+
+    let builds = snapshots().into_iter().map(|snapshot| snapshot.instance.build.unwrap()).collect::<Vec<String>>();
+
+    assert_eq!(builds, vec!["499", "498"]);
+    assert!(!Path::new("backups/before-497").exists());
+    assert!(Path::new("backups/before-498").exists());
+    assert!(save_snapshot(&install_paper("500"), None, 0).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn failed_upgrade_leaves_no_history() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+    let directory = TempDir::new().unwrap();
+    let _guard = enter(&directory).await;
+    let config = config(&directory);
+
+    let instance = install_paper("498");
+    let plan = plan_upgrade(&Paper {}, &instance, UpgradeTarget::SameVersion, &config, &OS::Linux, &http).await.unwrap();
+    upstreams.respond("/api.papermc.io/v2/projects/paper/versions/1.20.4/builds/499/downloads/paper-1.20.4-499.jar", ResponseTemplate::new(404)).await;
+
+    assert!(execute_upgrade(&Paper {}, &plan, false, &config, &OS::Linux, &http).await.is_err());
+    assert!(snapshots().is_empty());
+}
+
+#[test]
+fn rollback_without_history_fails() {
+    let directory = TempDir::new().unwrap();
+    let home = directory.path().join("home");
+    let server = directory.path().join("server");
+    fs::create_dir_all(&server).unwrap();
+
+    let rollback = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_minecraft_server_installer"))
+            .args(args)
+            .current_dir(&server)
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join("config"))
+            .env("XDG_DATA_HOME", home.join("data"))
+            .output()
+            .unwrap()
+    };
+
+    let output = rollback(&["rollback"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("There is no previous install to roll back to."));
+
+    let output = rollback(&["rollback", "--list"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No previous installs are saved."));
+}
+
+#[tokio::test]
+async fn applying_a_manifest_twice_changes_nothing() {
+    let upstreams = Upstreams::start().await;
+    let http = upstreams.http();
+    let directory = TempDir::new().unwrap();
+    let _guard = enter(&directory).await;
+    let config = config(&directory);
+
+    fs::write(MANIFEST_FILE, "distribution = \"paper\"\nminecraft_version = \"1.20.4\"\nram = 2\nport = 25570\n").unwrap();
+    let manifest = Manifest::load(MANIFEST_FILE).unwrap();
+
+    let changes = manifest.apply(&Paper {}, &config, &OS::Linux, &http).await.unwrap();
+
+    assert_eq!(changes, vec!["Installed Paper 1.20.4", "Updated user_jvm_args.txt", "Set server-port in server.properties"]);
+    assert_eq!(fs::read("server.jar").unwrap(), fixture("artifacts/paper-1.20.4-499.jar"));
+
+    let changes = manifest.apply(&Paper {}, &config, &OS::Linux, &http).await.unwrap();
+    let downloads = upstreams.requests("/api.papermc.io/v2/projects/paper/versions/1.20.4/builds/499/downloads/paper-1.20.4-499.jar").await;
+
+    assert_eq!(changes, Vec::<String>::new());
+    assert_eq!(downloads.len(), 1);
+}